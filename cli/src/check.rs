@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+use ksmap::check::{self, Severity};
+use ksmap::definitions;
+use ksmap::screen_map::ScreenMap;
+use libks::{map_bin, world_ini};
+
+use crate::cli::{CheckArgs, OutputFormat};
+
+pub fn run(args: &CheckArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = ScreenMap::new(map_bin::parse_map_file(level_dir.join("Map.bin"))?);
+    let ini = world_ini::load_ini_from_dir(&level_dir)?;
+    let mut defs = definitions::load_object_defs(&args.object_definitions)?;
+    definitions::insert_custom_obj_defs(&mut defs, &ini);
+
+    let min_severity = Severity::from(args.min_severity);
+    let problems: Vec<_> = check::check_level(&screens, &ini, &defs, &level_dir)
+        .into_iter()
+        .filter(|problem| problem.severity >= min_severity)
+        .collect();
+
+    match args.format {
+        OutputFormat::Text => {
+            for problem in &problems {
+                match problem.screen {
+                    Some(pos) => println!("{}: x{}y{}: {}", problem.severity, pos.0, pos.1, problem.message),
+                    None => println!("{}: {}", problem.severity, problem.message),
+                }
+            }
+            if problems.is_empty() {
+                println!("No problems found");
+            }
+        },
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&problems)?),
+    }
+
+    let n_errors = problems.iter()
+        .filter(|problem| problem.severity == Severity::Error)
+        .count();
+    if n_errors > 0 {
+        return Err(anyhow!("Found {n_errors} error(s)"));
+    }
+
+    Ok(())
+}
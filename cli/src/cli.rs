@@ -1,38 +1,62 @@
 use std::path::PathBuf;
 
-use clap::{Args, Parser};
+use clap::{Args, Parser, Subcommand};
+use ksmap::{check::Severity, drawing::{OverviewScale, SCREEN_SIZE}, id::ObjectId, partition::ZoneKey, search::TileQuery, synchronization::LaserStrategy};
 
 #[derive(Parser)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     /// 64-bit RNG seed. Must be between 1 and 16 hexadecimal digits
     #[arg(short = 's', long)]
     pub seed: Option<String>,
-    /// The maximum width of a single output image in pixels
-    #[arg(short = 'x', long, default_value = "60000")]
-    pub max_width: u64,
-    /// The maximum height of a single output image in pixels
-    #[arg(short = 'y', long, default_value = "48000")]
-    pub max_height: u64,
-    /// How to divide large maps that don't fit into one image
-    #[arg(value_enum, short = 'p', long, default_value = "islands")]
-    pub partitioner: PartitionStrategy,
-    /// Force the partitioner to be used even if the map fits in one image
-    #[arg(short = 'f', long)]
-    pub force: bool,
+    #[command(flatten)]
+    pub partition_args: PartitionArgs,
     /// Print the partition list, but don't render the map
     #[arg(long)]
     pub dry_run: bool,
-    #[command(flatten)]
-    pub islands_args: IslandsArgs,
-    #[command(flatten)]
-    pub grid_args: GridArgs,
     /// Draw objects that are only visible in the editor
     #[arg(long)]
     pub editor_only: bool,
-    /// Always pick a random laser phase (red/green) rather than the one with the most lasers
+    /// Draw where the player can stand instead of the level's graphics: solid tiles in white,
+    /// and hazards, collectables and interactive objects in red, yellow and blue
     #[arg(long)]
+    pub collision_map: bool,
+    /// Which laser phase (red/green) to show in each group of synchronized screens
+    #[arg(value_enum, long, default_value = "maximize")]
+    pub lasers: LaserStrategyArg,
+    /// Deprecated alias for `--lasers random`
+    #[arg(long, hide = true, conflicts_with = "lasers")]
     pub randomize_lasers: bool,
+    /// Synchronize animations across screen corners where objects touch diagonally
+    #[arg(long)]
+    pub sync_diagonals: bool,
+    /// Surround each image with dimmed screens from neighboring images when the map is partitioned.
+    /// Measured in screens (e.g. `1`) or pixels (e.g. `120px`)
+    #[arg(long, value_parser = parse_margin, default_value = "0")]
+    pub margin: (u32, u32),
+    /// Tint each screen by its animation sync group and label it with the group's
+    /// ID, animation time and laser phase
+    #[arg(long)]
+    pub sync_overlay: bool,
+    /// The scale of `overview.png`, a downscaled image of the whole world with each image
+    /// outlined and numbered. Only written when the map is partitioned
+    #[arg(value_enum, long, default_value = "tile")]
+    pub overview: OverviewArg,
+    /// Outline every collectable and label it with its number from the `collectables` command
+    #[arg(long)]
+    pub collectable_markers: bool,
+    /// Write the animation sync groups to a JSON file
+    #[arg(long, value_name = "FILE")]
+    pub export_sync_groups: Option<PathBuf>,
     /// Don't use the multithreaded PNG encoder
     #[arg(long)]
     pub single_threaded_encoder: bool,
@@ -51,7 +75,255 @@ pub struct Cli {
     #[arg(short, long = "output")]
     pub output_dir: Option<PathBuf>,
     /// Path to the level's directory or Map.bin
+    #[arg(required = true)]
+    pub level: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print statistics about a level without rendering it
+    Stats(StatsArgs),
+    /// List every collectable in a level with its position
+    Collectables(CollectablesArgs),
+    /// Report broken shifts, warps and custom objects
+    Check(CheckArgs),
+    /// Export the screens and how they connect as a graph
+    Graph(GraphArgs),
+    /// List every place an object or tileset tile is used
+    Find(FindArgs),
+    /// List tilesets, gradients and custom object images in the level's folder that it doesn't use
+    Unused(UnusedArgs),
+    /// Report how often each tile of each tileset is used
+    Tilesets(TilesetsArgs),
+    /// Find screens that are copies or near copies of each other
+    Duplicates(DuplicatesArgs),
+    /// Compare two versions of a level and draw the screens that changed
+    Diff(DiffArgs),
+}
+
+#[derive(Args)]
+pub struct StatsArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: OutputFormat,
+    /// Path to the level's directory or Map.bin
+    pub level: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CollectablesArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: ListFormat,
+    /// Path to the file containing object definitions
+    #[arg(long = "definitions", default_value = "mapper_objects.toml")]
+    pub object_definitions: PathBuf,
+    /// Path to the level's directory or Map.bin
+    pub level: PathBuf,
+}
+
+#[derive(Args)]
+pub struct CheckArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: OutputFormat,
+    /// Only report problems at least this severe
+    #[arg(value_enum, long, default_value = "info")]
+    pub min_severity: SeverityArg,
+    /// Path to the file containing object definitions
+    #[arg(long = "definitions", default_value = "mapper_objects.toml")]
+    pub object_definitions: PathBuf,
+    /// Path to the level's directory or Map.bin
+    pub level: PathBuf,
+}
+
+#[derive(Args)]
+pub struct GraphArgs {
+    #[arg(value_enum, long, default_value = "dot")]
+    pub format: GraphFormat,
+    /// Path to write the graph to instead of printing it
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Path to the level's directory or Map.bin
+    pub level: PathBuf,
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Graphml,
+    Json,
+}
+
+#[derive(Args)]
+pub struct UnusedArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: OutputFormat,
+    /// Path to the file containing object definitions
+    #[arg(long = "definitions", default_value = "mapper_objects.toml")]
+    pub object_definitions: PathBuf,
+    /// Path to the level's directory or Map.bin
+    pub level: PathBuf,
+}
+
+#[derive(Args)]
+pub struct TilesetsArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: OutputFormat,
+    /// Directory to write a heatmap of each tileset to
+    #[arg(long, value_name = "DIR")]
+    pub heatmaps: Option<PathBuf>,
+    /// Path to the KS data directory.
+    /// If unspecified, it will be located relative to the level directory
+    #[arg(long = "data")]
+    pub data_dir: Option<PathBuf>,
+    /// Path to the level's directory or Map.bin
+    pub level: PathBuf,
+}
+
+#[derive(Args)]
+pub struct DuplicatesArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: OutputFormat,
+    /// The most tiles and assets that near duplicates may differ by. 0 only finds exact duplicates
+    #[arg(short = 'd', long, default_value = "10")]
+    pub max_difference: usize,
+    /// Path to the level's directory or Map.bin
+    pub level: PathBuf,
+}
+
+#[derive(Args)]
+pub struct DiffArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: OutputFormat,
+    /// Directory to write the diff map and a side-by-side image of each changed screen to
+    #[arg(short, long, default_value = "diff")]
+    pub output: PathBuf,
+    /// Only list the changes without drawing them
+    #[arg(long)]
+    pub no_images: bool,
+    /// 64-bit RNG seed used to draw both versions. Must be between 1 and 16 hexadecimal digits
+    #[arg(short = 's', long)]
+    pub seed: Option<String>,
+    /// Path to the KS data directory.
+    /// If unspecified, it will be located relative to each level directory
+    #[arg(long = "data", help_heading = "Paths")]
+    pub data_dir: Option<PathBuf>,
+    /// Path to the directory containing object templates
+    #[arg(long = "templates", default_value = "Mapper Templates", help_heading = "Paths")]
+    pub templates_dir: PathBuf,
+    /// Path to the file containing object definitions
+    #[arg(long = "definitions", default_value = "mapper_objects.toml", help_heading = "Paths")]
+    pub object_definitions: PathBuf,
+    /// Path to the old version's directory or Map.bin
+    pub old: PathBuf,
+    /// Path to the new version's directory or Map.bin
+    pub new: PathBuf,
+}
+
+#[derive(Args)]
+pub struct FindArgs {
+    #[arg(value_enum, long, default_value = "text")]
+    pub format: FindFormat,
+    /// Path to write the highlight image to
+    #[arg(short, long, required_if_eq("format", "image"))]
+    pub output: Option<PathBuf>,
+    /// Layer to search, can be given more than once.
+    /// Defaults to the object layers 4-7; use 0-3 to search for tileset tiles
+    #[arg(short, long, value_parser = clap::value_parser!(u8).range(0..8))]
+    pub layer: Vec<u8>,
+    /// Also match custom objects that override the object
+    #[arg(long)]
+    pub match_originals: bool,
+    /// Size of a tile in the highlight image in pixels
+    #[arg(long, default_value = "4", value_parser = clap::value_parser!(u32).range(1..=24))]
+    pub tile_size: u32,
+    /// Path to the file containing object definitions
+    #[arg(long = "definitions", default_value = "mapper_objects.toml")]
+    pub object_definitions: PathBuf,
+    /// Path to the level's directory or Map.bin
     pub level: PathBuf,
+    /// The object or tile to look for, e.g. `12-5`, or just a bank, e.g. `12`, to match any object
+    /// in it. On tile layers, bank 0 is tileset A and bank 1 is tileset B
+    #[arg(value_parser = parse_tile_query)]
+    pub query: TileQuery,
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum FindFormat {
+    #[default]
+    Text,
+    Json,
+    /// A map of the world with the matches highlighted, written to --output
+    Image,
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum SeverityArg {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+impl From<SeverityArg> for Severity {
+    fn from(value: SeverityArg) -> Self {
+        match value {
+            SeverityArg::Info => Severity::Info,
+            SeverityArg::Warning => Severity::Warning,
+            SeverityArg::Error => Severity::Error,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum ListFormat {
+    #[default]
+    Text,
+    Csv,
+    Json,
+}
+
+#[derive(Args)]
+pub struct PartitionArgs {
+    /// The maximum width of a single output image in pixels.
+    /// Defaults to 60000, or no limit if --memory-budget is set
+    #[arg(short = 'x', long)]
+    pub max_width: Option<u64>,
+    /// The maximum height of a single output image in pixels.
+    /// Defaults to 48000, or no limit if --memory-budget is set
+    #[arg(short = 'y', long)]
+    pub max_height: Option<u64>,
+    /// The most memory to use while drawing a single image, e.g. `4GiB` or `512MB`,
+    /// including its margin and the overview. Images are sized by area, so they can
+    /// be long and thin as long as they fit
+    #[arg(long, value_name = "SIZE", value_parser = parse_byte_size)]
+    pub memory_budget: Option<u64>,
+    /// How to divide large maps that don't fit into one image
+    #[arg(value_enum, short = 'p', long, default_value = "islands")]
+    pub partitioner: PartitionStrategy,
+    /// Force the partitioner to be used even if the map fits in one image
+    #[arg(short = 'f', long)]
+    pub force: bool,
+    /// Path to a TOML file listing named regions to render as separate images.
+    /// Screens outside the regions are divided by the partitioner if the layout
+    /// sets `leftovers = "Fallback"`. Regions larger than the maximum size are
+    /// split by the partitioner into numbered pieces
+    #[arg(long, value_name = "FILE")]
+    pub layout: Option<PathBuf>,
+    #[command(flatten)]
+    pub islands_args: IslandsArgs,
+    #[command(flatten)]
+    pub grid_args: GridArgs,
+    #[command(flatten)]
+    pub compact_args: CompactArgs,
+    #[command(flatten)]
+    pub zones_args: ZonesArgs,
 }
 
 #[derive(Clone, Copy, Default, clap::ValueEnum)]
@@ -62,11 +334,72 @@ pub enum PartitionStrategy {
     Islands,
     /// Divide the map into a fixed grid
     Grid,
+    /// Divide the map into islands, then cut islands into rectangles
+    /// that leave as few empty screens as possible
+    Compact,
+    /// Divide the map into areas of adjacent screens that share the same assets.
+    /// Areas that are still too large will be subdivided like islands
+    Zones,
+    /// Divide the map into areas the player can travel between, following warps and shifts.
+    /// Areas that are too large are split where they would exceed the maximum size
+    Connected,
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum LaserStrategyArg {
+    /// Show the phase with the most lasers in each group
+    #[default]
+    Maximize,
+    /// Pick a random phase for each group
+    Random,
+    /// Show the phase with the most lasers in the whole level in every group
+    World,
+    /// Show red lasers everywhere
+    Red,
+    /// Show green lasers everywhere
+    Green,
+    /// Draw both phases with transparency
+    Overlay,
+}
+
+impl From<LaserStrategyArg> for LaserStrategy {
+    fn from(value: LaserStrategyArg) -> Self {
+        match value {
+            LaserStrategyArg::Maximize => LaserStrategy::Maximize,
+            LaserStrategyArg::Random => LaserStrategy::Random,
+            LaserStrategyArg::World => LaserStrategy::WorldConsistent,
+            LaserStrategyArg::Red => LaserStrategy::ForceRed,
+            LaserStrategyArg::Green => LaserStrategy::ForceGreen,
+            LaserStrategyArg::Overlay => LaserStrategy::Overlay,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+pub enum OverviewArg {
+    /// One pixel per tile
+    #[default]
+    Tile,
+    /// One pixel per screen
+    Screen,
+    /// Don't write an overview
+    Off,
+}
+
+impl OverviewArg {
+    pub fn scale(self) -> Option<OverviewScale> {
+        match self {
+            OverviewArg::Tile => Some(OverviewScale::Tile),
+            OverviewArg::Screen => Some(OverviewScale::Screen),
+            OverviewArg::Off => None,
+        }
+    }
 }
 
 #[derive(Args)]
 pub struct IslandsArgs {
     /// The number of empty screens allowed between the screens of an island.
+    /// Also used by the compact and zones partitioners.
     /// -g sets min and max to the same value
     #[arg(short = 'g', long, default_value = "10", help_heading = "Islands partitioner")]
     pub max_gap: u64,
@@ -87,3 +420,113 @@ pub struct GridArgs {
     #[arg(short, long, help_heading = "Grid partitioner")]
     pub cols: Option<u64>,
 }
+
+#[derive(Args)]
+pub struct CompactArgs {
+    /// The fraction of an image that should be covered by screens (0-1).
+    /// Images below this are cut further if it saves space
+    #[arg(long, default_value = "0.5", help_heading = "Compact partitioner")]
+    pub min_fill: f64,
+}
+
+#[derive(Args)]
+pub struct ZonesArgs {
+    /// The assets that adjacent screens must share to be in the same zone
+    #[arg(
+        value_enum,
+        long,
+        value_delimiter = ',',
+        default_value = "tileset-a,tileset-b",
+        help_heading = "Zones partitioner",
+    )]
+    pub zone_by: Vec<ZoneKeyArg>,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ZoneKeyArg {
+    TilesetA,
+    TilesetB,
+    AtmosphereA,
+    AtmosphereB,
+    Music,
+    Gradient,
+}
+
+impl From<ZoneKeyArg> for ZoneKey {
+    fn from(value: ZoneKeyArg) -> Self {
+        match value {
+            ZoneKeyArg::TilesetA => ZoneKey::TilesetA,
+            ZoneKeyArg::TilesetB => ZoneKey::TilesetB,
+            ZoneKeyArg::AtmosphereA => ZoneKey::AtmosphereA,
+            ZoneKeyArg::AtmosphereB => ZoneKey::AtmosphereB,
+            ZoneKeyArg::Music => ZoneKey::Music,
+            ZoneKeyArg::Gradient => ZoneKey::Gradient,
+        }
+    }
+}
+
+fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "m" | "mb" => 1000_u64.pow(2),
+        "g" | "gb" => 1000_u64.pow(3),
+        "t" | "tb" => 1000_u64.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        unit => return Err(format!("unknown unit `{unit}`")),
+    };
+    let number = str::parse::<f64>(number)
+        .map_err(|err| err.to_string())?;
+    
+    Ok((number * multiplier as f64) as u64)
+}
+
+fn parse_tile_query(value: &str) -> Result<TileQuery, String> {
+    if value.contains('-') {
+        let id = ObjectId::try_from(value)
+            .map_err(|err| err.to_string())?;
+        Ok(TileQuery::Tile(id.0))
+    }
+    else {
+        let bank = str::parse::<u8>(value)
+            .map_err(|err| err.to_string())?;
+        Ok(TileQuery::Bank(bank))
+    }
+}
+
+fn parse_margin(value: &str) -> Result<(u32, u32), String> {
+    if let Some(pixels) = value.strip_suffix("px") {
+        let pixels = str::parse::<u32>(pixels)
+            .map_err(|err| err.to_string())?;
+        Ok((pixels, pixels))
+    }
+    else {
+        let screens = str::parse::<u32>(value)
+            .map_err(|err| err.to_string())?;
+        screens.checked_mul(SCREEN_SIZE.0)
+            .zip(screens.checked_mul(SCREEN_SIZE.1))
+            .ok_or_else(|| format!("{screens} screens is too large"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn margins_in_screens_and_pixels() {
+        assert_eq!(parse_margin("0"), Ok((0, 0)));
+        assert_eq!(parse_margin("2"), Ok((2 * SCREEN_SIZE.0, 2 * SCREEN_SIZE.1)));
+        assert_eq!(parse_margin("120px"), Ok((120, 120)));
+        assert!(parse_margin("8000000").is_err());
+        assert!(parse_margin("-1").is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use ksmap::analysis::{self, Collectable};
+use ksmap::definitions;
+use libks::{map_bin, world_ini};
+
+use crate::cli::{CollectablesArgs, ListFormat};
+
+pub fn run(args: &CollectablesArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))?;
+    let ini = world_ini::load_ini_from_dir(&level_dir)?;
+    let mut defs = definitions::load_object_defs(&args.object_definitions)?;
+    definitions::insert_custom_obj_defs(&mut defs, &ini);
+
+    let collectables = analysis::list_collectables(&screens, &defs);
+    match args.format {
+        ListFormat::Text => print_text(&collectables),
+        ListFormat::Csv => print_csv(&collectables),
+        ListFormat::Json => println!("{}", serde_json::to_string_pretty(&collectables)?),
+    }
+
+    Ok(())
+}
+
+fn print_text(collectables: &[Collectable]) {
+    for (i, collectable) in collectables.iter().enumerate() {
+        let (tile_x, tile_y) = collectable.tile_position();
+        let object = match &collectable.original {
+            Some(original) => format!("{} ({original})", collectable.object),
+            None => collectable.object.clone(),
+        };
+        println!(
+            "{:4}: {:8} {:20} x{}y{} layer {} tile {} ({tile_x}, {tile_y})",
+            i + 1,
+            format!("{:?}", collectable.kind),
+            object,
+            collectable.screen.0,
+            collectable.screen.1,
+            collectable.layer,
+            collectable.tile,
+        );
+    }
+}
+
+fn print_csv(collectables: &[Collectable]) {
+    println!("number,kind,object,original,screen_x,screen_y,layer,tile,tile_x,tile_y");
+    for (i, collectable) in collectables.iter().enumerate() {
+        let (tile_x, tile_y) = collectable.tile_position();
+        println!(
+            "{},{:?},{},{},{},{},{},{},{tile_x},{tile_y}",
+            i + 1,
+            collectable.kind,
+            collectable.object,
+            collectable.original.as_deref().unwrap_or(""),
+            collectable.screen.0,
+            collectable.screen.1,
+            collectable.layer,
+            collectable.tile,
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use image::{Rgba, RgbaImage};
+use ksmap::analysis;
+use ksmap::definitions::{self, ObjectDefs};
+use ksmap::diff::{self, ChangeKind, ScreenChange};
+use ksmap::drawing::{self, DrawOptions, Overview, OverviewScale};
+use ksmap::graphics::Graphics;
+use ksmap::partition::Bounds;
+use ksmap::screen_map::ScreenMap;
+use ksmap::seed::MapSeed;
+use ksmap::synchronization::{SyncOptions, WorldSync};
+use libks::{ScreenCoord, map_bin, world_ini};
+use libks_ini::Ini;
+use rustc_hash::FxHashMap;
+
+use crate::cli::{DiffArgs, OutputFormat};
+
+/// Brightness of unchanged screens on the diff map
+const UNCHANGED_BRIGHTNESS: f32 = 0.35;
+const ADDED_COLOR: Rgba<u8> = Rgba([64, 255, 64, 255]);
+const REMOVED_COLOR: Rgba<u8> = Rgba([255, 64, 64, 255]);
+const MODIFIED_COLOR: Rgba<u8> = Rgba([255, 220, 0, 255]);
+
+struct Level {
+    dir: PathBuf,
+    screens: ScreenMap,
+    ini: Ini,
+    defs: ObjectDefs,
+}
+
+impl Level {
+    fn load(path: &Path, args: &DiffArgs) -> Result<Self> {
+        let dir = crate::find_level_dir(path);
+        let screens = ScreenMap::new(map_bin::parse_map_file(dir.join("Map.bin"))?);
+        let ini = world_ini::load_ini_from_dir(&dir)?;
+        let mut defs = definitions::load_object_defs(&args.object_definitions)?;
+        definitions::insert_custom_obj_defs(&mut defs, &ini);
+
+        Ok(Self {
+            dir,
+            screens,
+            ini,
+            defs,
+        })
+    }
+
+    /// Draws the screens at `positions` one at a time and passes each image to `f`
+    fn draw_screens(
+        &self,
+        positions: &[ScreenCoord],
+        seed: MapSeed,
+        args: &DiffArgs,
+        mut f: impl FnMut(ScreenCoord, RgbaImage) -> Result<()>,
+    ) -> Result<()> {
+        let data_dir = args.data_dir.clone().unwrap_or_else(|| self.dir.join("../../Data"));
+        let mut gfx = Graphics::new(data_dir, &self.dir, &args.templates_dir, &self.defs);
+        let assets_used = analysis::list_assets(&self.screens, &self.defs);
+        gfx.load_tilesets(&assets_used.tilesets)?;
+        gfx.load_gradients(&assets_used.gradients)?;
+        gfx.load_objects(&assets_used.objects)?;
+
+        let world_sync = WorldSync::new(seed, &self.screens, &self.defs, &SyncOptions::default());
+        for pos in positions {
+            let Some(index_screen) = self.screens.index_of(pos) else { continue };
+            let image = drawing::draw_screen(
+                seed,
+                &self.screens[index_screen],
+                index_screen,
+                &gfx,
+                &self.defs,
+                &self.ini,
+                DrawOptions::default(),
+                &world_sync,
+            )?;
+            f(*pos, image)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn run(args: &DiffArgs) -> Result<()> {
+    let old = Level::load(&args.old, args)?;
+    let new = Level::load(&args.new, args)?;
+
+    let changes = diff::diff_screen_maps(&old.screens, &new.screens);
+    match args.format {
+        OutputFormat::Text => print_text(&changes),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&changes)?),
+    }
+
+    if args.no_images || changes.is_empty() {
+        return Ok(());
+    }
+
+    let seed = crate::parse_seed(args.seed.clone())?;
+    if let OutputFormat::Text = args.format {
+        println!("Seed: {seed}");
+    }
+    fs::create_dir_all(&args.output)?;
+    draw_diff(&old, &new, &changes, seed, args)
+}
+
+/// Writes a downscaled map of the new version with the changed screens outlined and the rest
+/// dimmed, and an old and new image of each changed screen side by side
+fn draw_diff(old: &Level, new: &Level, changes: &[ScreenChange], seed: MapSeed, args: &DiffArgs) -> Result<()> {
+    let kinds: FxHashMap<_, _> = changes.iter()
+        .map(|change| (change.position, change.kind))
+        .collect();
+    let bounds = Bounds::from_iter(old.screens.iter_positions().chain(new.screens.iter_positions()));
+    let mut overview = Overview::with_bounds(bounds, OverviewScale::Tile)?;
+
+    // Only the changed screens of the old version are needed, and they're kept until the new
+    // version of the screen is drawn
+    let old_positions: Vec<_> = changes.iter()
+        .filter(|change| change.kind != ChangeKind::Added)
+        .map(|change| change.position)
+        .collect();
+    let mut old_images = FxHashMap::default();
+    old.draw_screens(&old_positions, seed, args, |pos, image| {
+        old_images.insert(pos, image);
+        Ok(())
+    })?;
+
+    let new_positions: Vec<_> = new.screens.iter_positions()
+        .copied()
+        .collect();
+    new.draw_screens(&new_positions, seed, args, |pos, image| {
+        overview.add_screen(&image, pos);
+        if kinds.contains_key(&pos) {
+            let comparison = drawing::draw_screen_comparison(old_images.remove(&pos).as_ref(), Some(&image));
+            drawing::export_canvas(comparison, &args.output.join(format!("x{}y{}.png", pos.0, pos.1)))?;
+        }
+        else {
+            overview.dim_screen(pos, UNCHANGED_BRIGHTNESS);
+        }
+        Ok(())
+    })?;
+
+    // Whatever is left was removed. Only unchanged screens are dimmed
+    for (pos, image) in old_images {
+        overview.add_screen(&image, pos);
+        let comparison = drawing::draw_screen_comparison(Some(&image), None);
+        drawing::export_canvas(comparison, &args.output.join(format!("x{}y{}.png", pos.0, pos.1)))?;
+    }
+
+    for change in changes {
+        let color = match change.kind {
+            ChangeKind::Added => ADDED_COLOR,
+            ChangeKind::Removed => REMOVED_COLOR,
+            ChangeKind::Modified => MODIFIED_COLOR,
+        };
+        overview.outline_screen(change.position, color);
+    }
+    drawing::export_canvas(overview.into_image(), &args.output.join("diff.png"))?;
+
+    println!("Wrote {} comparison(s) and the diff map to {}", changes.len(), args.output.display());
+    Ok(())
+}
+
+fn print_text(changes: &[ScreenChange]) {
+    if changes.is_empty() {
+        println!("No screens changed");
+        return;
+    }
+
+    for kind in [ChangeKind::Added, ChangeKind::Removed, ChangeKind::Modified] {
+        let count = changes.iter()
+            .filter(|change| change.kind == kind)
+            .count();
+        println!("{:<9} {count}", format!("{kind:?}:"));
+    }
+
+    println!();
+    for change in changes {
+        let unit = match change.kind {
+            ChangeKind::Modified => "differences",
+            ChangeKind::Added | ChangeKind::Removed => "tiles",
+        };
+        println!(
+            "  {:<8} x{}y{} ({} {unit})",
+            format!("{:?}", change.kind),
+            change.position.0,
+            change.position.1,
+            change.difference,
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use anyhow::Result;
+use ksmap::analysis::{self, DuplicateScreens};
+use libks::ScreenCoord;
+use libks::map_bin;
+
+use crate::cli::{DuplicatesArgs, OutputFormat};
+
+pub fn run(args: &DuplicatesArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))?;
+
+    let duplicates = analysis::find_duplicate_screens(&screens, args.max_difference);
+    match args.format {
+        OutputFormat::Text => print_text(&duplicates, args.max_difference),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&duplicates)?),
+    }
+
+    Ok(())
+}
+
+fn print_text(duplicates: &DuplicateScreens, max_difference: usize) {
+    println!("Exact duplicates: {} group(s)", duplicates.exact.len());
+    for group in &duplicates.exact {
+        println!("  {}", format_screens(group));
+    }
+
+    if max_difference > 0 {
+        println!();
+        println!("Near duplicates (up to {max_difference} differences): {} group(s)", duplicates.near.len());
+        for cluster in &duplicates.near {
+            println!("  {} (up to {})", format_screens(&cluster.screens), cluster.max_difference);
+        }
+    }
+}
+
+fn format_screens(screens: &[ScreenCoord]) -> String {
+    screens.iter()
+        .map(|pos| format!("x{}y{}", pos.0, pos.1))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,58 @@
+use anyhow::{anyhow, Result};
+use ksmap::definitions;
+use ksmap::drawing;
+use ksmap::screen_map::ScreenMap;
+use ksmap::search::{self, SearchMatch, SearchQuery};
+use libks::{map_bin, world_ini};
+
+use crate::cli::{FindArgs, FindFormat};
+
+pub fn run(args: &FindArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = ScreenMap::new(map_bin::parse_map_file(level_dir.join("Map.bin"))?);
+    let ini = world_ini::load_ini_from_dir(&level_dir)?;
+    let mut defs = definitions::load_object_defs(&args.object_definitions)?;
+    definitions::insert_custom_obj_defs(&mut defs, &ini);
+
+    let mut query = SearchQuery::new(args.query);
+    if !args.layer.is_empty() {
+        query.layers = args.layer.iter()
+            .map(|layer| *layer as usize)
+            .collect();
+    }
+    query.match_originals = args.match_originals;
+
+    let matches = search::find_tiles(&screens, &query, &defs);
+    match args.format {
+        FindFormat::Text => print_text(&matches),
+        FindFormat::Json => println!("{}", serde_json::to_string_pretty(&matches)?),
+        FindFormat::Image => {
+            let Some(path) = &args.output else {
+                return Err(anyhow!("--format image needs --output"));
+            };
+            let canvas = drawing::draw_search_highlights(&screens, &matches, args.tile_size)?;
+            drawing::export_canvas(canvas, path)?;
+            println!("Highlighted {} match(es) in {}", matches.len(), path.display());
+        },
+    }
+
+    Ok(())
+}
+
+fn print_text(matches: &[SearchMatch]) {
+    for found in matches {
+        let (tile_x, tile_y) = found.tile_position();
+        let object = match &found.original {
+            Some(original) => format!("{} ({original})", found.object),
+            None => found.object.clone(),
+        };
+        println!(
+            "x{}y{} layer {} tile {} ({tile_x}, {tile_y}): {object}",
+            found.screen.0,
+            found.screen.1,
+            found.layer,
+            found.tile,
+        );
+    }
+    println!("{} match(es)", matches.len());
+}
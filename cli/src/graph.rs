@@ -0,0 +1,28 @@
+use std::fs;
+
+use anyhow::Result;
+use ksmap::screen_graph;
+use ksmap::screen_map::ScreenMap;
+use libks::{map_bin, world_ini};
+
+use crate::cli::{GraphArgs, GraphFormat};
+
+pub fn run(args: &GraphArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = ScreenMap::new(map_bin::parse_map_file(level_dir.join("Map.bin"))?);
+    let ini = world_ini::load_ini_from_dir(&level_dir)?;
+
+    let graph = screen_graph::build_screen_graph(&screens, &ini);
+    let text = match args.format {
+        GraphFormat::Dot => screen_graph::to_dot(&graph),
+        GraphFormat::Graphml => screen_graph::to_graphml(&graph),
+        GraphFormat::Json => serde_json::to_string_pretty(&screen_graph::to_json(&graph))? + "\n",
+    };
+
+    match &args.output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{text}"),
+    }
+
+    Ok(())
+}
@@ -1,47 +1,70 @@
+mod check;
 mod cli;
+mod collectables;
+mod diff;
+mod duplicates;
+mod find;
+mod graph;
+mod manifest;
+mod stats;
+mod tilesets;
 mod timing;
+mod unused;
 
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::Parser;
-use ksmap::partition::{GridPartitioner, IslandsPartitioner, Partition, Partitioner};
+use ksmap::partition::{self, Bounds, CompactPartitioner, ConnectivityPartitioner, GridPartitioner, IslandsPartitioner, ManualPartitioner, Partition, Partitioner, SizeLimit, ZonePartitioner};
 use ksmap::seed::MapSeed;
 use ksmap::synchronization::{SyncOptions, WorldSync};
 use libks::{map_bin, world_ini};
+use libks_ini::Ini;
 
-use ksmap::{analysis, definitions};
-use ksmap::drawing::{self, DrawContext, DrawOptions};
+use ksmap::{analysis, definitions, links};
+use ksmap::drawing::{self, DrawContext, DrawOptions, Overview, OverviewScale, SCREEN_SIZE};
 use ksmap::graphics::Graphics;
 use ksmap::screen_map::ScreenMap;
 
-use crate::cli::{Cli, GridArgs, IslandsArgs, PartitionStrategy};
+use crate::cli::{Cli, Command, LaserStrategyArg, PartitionArgs, PartitionStrategy};
+use crate::manifest::{Manifest, ManifestImage};
 use crate::timing::Timespan;
 
+const DEFAULT_MAX_WIDTH: u64 = 60000;
+const DEFAULT_MAX_HEIGHT: u64 = 48000;
+/// Memory set aside for tilesets, gradients and object sprites when sizing images by memory budget
+const ASSET_CACHE_RESERVE: u64 = 256 << 20;
+/// Extra memory the PNG encoder needs for filtered and compressed rows, relative to the canvas
+const ENCODER_OVERHEAD: f64 = 0.5;
+
 fn main() -> Result<()> {
-    let mut total_time = Timespan::begin();
     let cli = Cli::parse();
+    
+    match cli.command {
+        Some(Command::Stats(args)) => stats::run(&args),
+        Some(Command::Collectables(args)) => collectables::run(&args),
+        Some(Command::Check(args)) => check::run(&args),
+        Some(Command::Graph(args)) => graph::run(&args),
+        Some(Command::Find(args)) => find::run(&args),
+        Some(Command::Unused(args)) => unused::run(&args),
+        Some(Command::Tilesets(args)) => tilesets::run(&args),
+        Some(Command::Duplicates(args)) => duplicates::run(&args),
+        Some(Command::Diff(args)) => diff::run(&args),
+        None => render(cli),
+    }
+}
 
-    let seed = match cli.seed.map(MapSeed::try_from) {
-        Some(Ok(seed)) => seed,
-        Some(Err(err)) => {
-            eprintln!("Failed to parse seed. The seed must be 1-16 hex digits (0-9 A-F).");
-            return Err(err.into());
-        },
-        None => MapSeed::random(),
+fn render(cli: Cli) -> Result<()> {
+    let mut total_time = Timespan::begin();
+    let Some(level) = &cli.level else {
+        return Err(anyhow!("Missing level path"));
     };
+
+    let seed = parse_seed(cli.seed)?;
     println!("Seed: {seed}");
     
-    let level_dir = if cli.level.is_dir() {
-            cli.level
-        }
-        else {
-            cli.level
-                .parent()
-                .unwrap_or("".as_ref())
-                .to_owned()
-        };
+    let level_dir = find_level_dir(level);
 
     let screen_map = time_it!("Loading map", {
         let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))?;
@@ -49,23 +72,17 @@ fn main() -> Result<()> {
         screen_map
     });
     
+    let ini = world_ini::load_ini_from_dir(&level_dir)?;
+    
     if cli.dry_run {
         println!();
-        make_partitions(&screen_map,
-            cli.max_width,
-            cli.max_height,
-            cli.partitioner,
-            cli.islands_args,
-            cli.grid_args,
-            cli.force);
+        make_partitions(&screen_map, &ini, &cli.partition_args, cli.margin, cli.overview.scale())?;
         total_time.end();
         println!();
         println!("Finished in {total_time}");
         return Ok(());
     }
     
-    let ini = world_ini::load_ini_from_dir(&level_dir)?;
-    
     let object_defs = time_it!("Loading definitions", {
         let mut defs = definitions::load_object_defs(cli.object_definitions)?;
         definitions::insert_custom_obj_defs(&mut defs, &ini);
@@ -88,23 +105,32 @@ fn main() -> Result<()> {
     });
     
     let world_sync = time_it!("Synchronizing map", {
+        let laser_strategy = if cli.randomize_lasers {
+            eprintln!("--randomize-lasers is deprecated, use --lasers random instead");
+            LaserStrategyArg::Random
+        }
+        else {
+            cli.lasers
+        };
         let sync_options = SyncOptions {
-            maximize_visible_lasers: !cli.randomize_lasers,
+            laser_strategy: laser_strategy.into(),
+            sync_diagonals: cli.sync_diagonals,
         };
         WorldSync::new(seed, &screen_map, &object_defs, &sync_options)
     });
     
+    if let Some(path) = &cli.export_sync_groups {
+        let groups = world_sync.describe_groups(&screen_map);
+        fs::write(path, serde_json::to_string_pretty(&groups)?)?;
+        println!("Exported {} sync groups to {}", groups.len(), path.display());
+    }
+    
     println!();
-    let partitions = make_partitions(&screen_map,
-        cli.max_width,
-        cli.max_height,
-        cli.partitioner,
-        cli.islands_args,
-        cli.grid_args,
-        cli.force);
+    let partitions = make_partitions(&screen_map, &ini, &cli.partition_args, cli.margin, cli.overview.scale())?;
 
     let draw_options = DrawOptions {
         editor_only: cli.editor_only,
+        collision_map: cli.collision_map,
     };
     let draw_context = DrawContext {
         seed,
@@ -125,18 +151,44 @@ fn main() -> Result<()> {
     if output_is_dir {
         fs::create_dir_all(&output_dir)?;
     }
+    let margin = if output_is_dir { cli.margin } else { (0, 0) };
+    let mut manifest = Manifest {
+        seed: seed.to_string(),
+        images: Vec::new(),
+    };
+    let collectables = if cli.collectable_markers {
+            analysis::list_collectables(&screen_map, &object_defs)
+        }
+        else {
+            Vec::new()
+        };
+    let mut overview = match cli.overview.scale() {
+        Some(scale) if output_is_dir => Some(Overview::new(&partitions, scale)?),
+        _ => None,
+    };
     
     println!();
     for (i, partition) in partitions.iter().enumerate() {
         let bounds = partition.bounds();
-        println!("{bounds} ({}/{})", i + 1, partitions.len());
+        let label = partition_label(partition);
+        println!("{label} ({}/{})", i + 1, partitions.len());
         
-        let canvas = time_it!("    Drawing", {
-            drawing::draw_partition(draw_context, &partition)?
+        let mut canvas = time_it!("    Drawing", {
+            drawing::draw_partition_with_margin(draw_context, &partition, margin)?
         });
+        if cli.sync_overlay {
+            drawing::draw_sync_overlay(&mut canvas, partition, margin, &screen_map, &world_sync);
+        }
+        if cli.collectable_markers {
+            drawing::draw_collectable_markers(&mut canvas, partition, margin, &collectables);
+        }
+        if let Some(overview) = &mut overview {
+            overview.add_partition(&canvas, partition, margin);
+        }
         
         let path: &Path = if output_is_dir {
-                let file_name = format!("{bounds}.png");
+                let file_name = format!("{label}.png");
+                manifest.images.push(ManifestImage::new(file_name.clone(), &bounds, canvas.dimensions(), margin));
                 &output_dir.join(file_name)
             }
             else if output_dir.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("png")) {
@@ -155,40 +207,89 @@ fn main() -> Result<()> {
             }
         });
     }
+    
+    if let Some(mut overview) = overview {
+        overview.draw_outlines(&partitions);
+        time_it!("Exporting overview", {
+            drawing::export_canvas(overview.into_image(), &output_dir.join("overview.png"))?
+        });
+    }
     println!();
     
+    if output_is_dir {
+        fs::write(output_dir.join("manifest.json"), serde_json::to_string_pretty(&manifest)?)?;
+    }
+    
     total_time.end();
     println!("Finished in {total_time}");
 
     Ok(())
 }
 
+/// `margin` and `overview` are only used to size images by memory budget
 fn make_partitions(
     screen_map: &ScreenMap,
-    max_width: u64,
-    max_height: u64,
-    partitioner: PartitionStrategy,
-    islands_args: IslandsArgs,
-    grid_args: GridArgs,
-    force: bool,
-) -> Vec<Partition> {
-    let max_size = (
-        u64::max(1, max_width / 600),
-        u64::max(1, max_height / 240),
+    ini: &Ini,
+    args: &PartitionArgs,
+    margin: (u32, u32),
+    overview: Option<OverviewScale>,
+) -> Result<Vec<Partition>> {
+    let default_width = if args.memory_budget.is_some() { u64::MAX } else { DEFAULT_MAX_WIDTH };
+    let default_height = if args.memory_budget.is_some() { u64::MAX } else { DEFAULT_MAX_HEIGHT };
+    let mut max_size = SizeLimit::new(
+        u64::max(1, args.max_width.unwrap_or(default_width) / SCREEN_SIZE.0 as u64),
+        u64::max(1, args.max_height.unwrap_or(default_height) / SCREEN_SIZE.1 as u64),
     );
+    if let Some(budget) = args.memory_budget {
+        // The overview is kept for the whole run, so it's only written if there are several
+        // images, but that isn't known until after partitioning
+        let overview_bytes = overview.map_or(0, |scale| {
+            let bounds = Bounds::from_iter(screen_map.iter_positions());
+            overview_size_in_bytes(&bounds, scale)
+        });
+        max_size = max_size.with_max_area(max_area_for_budget(budget, margin, overview_bytes)?);
+    }
     
-    let strategy: Box<dyn Partitioner> = match partitioner {
+    let strategy: Box<dyn Partitioner> = match args.partitioner {
         PartitionStrategy::Islands => Box::new(IslandsPartitioner {
             max_size,
-            gap: islands_args.min_gap..=islands_args.max_gap,
-            force: force,
+            gap: args.islands_args.min_gap..=args.islands_args.max_gap,
+            force: args.force,
         }),
         PartitionStrategy::Grid => Box::new(GridPartitioner {
             max_size,
-            rows: grid_args.rows,
-            cols: grid_args.cols,
-            force: force,
+            rows: args.grid_args.rows,
+            cols: args.grid_args.cols,
+            force: args.force,
         }),
+        PartitionStrategy::Compact => Box::new(CompactPartitioner {
+            max_size,
+            gap: args.islands_args.max_gap,
+            min_fill: args.compact_args.min_fill,
+            force: args.force,
+        }),
+        PartitionStrategy::Zones => Box::new(ZonePartitioner {
+            max_size,
+            keys: args.zones_args.zone_by.iter()
+                .map(|&key| key.into())
+                .collect(),
+            gap: args.islands_args.min_gap..=args.islands_args.max_gap,
+            force: args.force,
+        }),
+        PartitionStrategy::Connected => Box::new(ConnectivityPartitioner {
+            max_size,
+            links: links::find_screen_links(screen_map, ini),
+            force: args.force,
+        }),
+    };
+    
+    let strategy: Box<dyn Partitioner> = match &args.layout {
+        Some(path) => Box::new(ManualPartitioner {
+            layout: partition::load_layout(path)?,
+            max_size,
+            fallback: Some(strategy),
+        }),
+        None => strategy,
     };
     
     let partitions = time_it!("Partitioning:", {
@@ -197,8 +298,126 @@ fn make_partitions(
     
     for (i, partition) in partitions.iter().enumerate() {
         let bounds = partition.bounds();
-        println!("   {:2}: {:24} {}x{}", i + 1, bounds.to_string(), bounds.width() * 600, bounds.height() * 240);
+        println!("   {:2}: {:24} {}x{}", i + 1, partition_label(partition), bounds.width() * SCREEN_SIZE.0 as u64, bounds.height() * SCREEN_SIZE.1 as u64);
     }
     
-    partitions
+    Ok(partitions)
+}
+
+/// Finds how many screens an image can cover without drawing and encoding it using more than
+/// `budget` bytes, with `margin` pixels of padding on each side and `overview_bytes` already taken
+fn max_area_for_budget(budget: u64, margin: (u32, u32), overview_bytes: u64) -> Result<u64> {
+    let Some(canvas_budget) = budget.checked_sub(ASSET_CACHE_RESERVE + overview_bytes) else {
+        return Err(anyhow!(
+            "Memory budget must be more than {} MiB to leave room for assets and the overview",
+            (ASSET_CACHE_RESERVE + overview_bytes) >> 20,
+        ));
+    };
+    
+    // An image of `area` screens is w x h screens with w + h <= area + 1, so the margin along its
+    // sides is at most one side's worth of pixels per screen, plus one more and the corners
+    let (screen_width, screen_height) = (SCREEN_SIZE.0 as u64, SCREEN_SIZE.1 as u64);
+    let (margin_x, margin_y) = (margin.0 as u64, margin.1 as u64);
+    let margin_per_screen = u64::max(2 * margin_x * screen_height, 2 * margin_y * screen_width);
+    let margin_fixed = margin_per_screen + 4 * margin_x * margin_y;
+    
+    let bytes_for_pixels = |pixels: u64| (pixels as f64 * 4.0 * (1.0 + ENCODER_OVERHEAD)) as u64;
+    let bytes_per_screen = bytes_for_pixels(screen_width * screen_height + margin_per_screen);
+    let max_area = canvas_budget.saturating_sub(bytes_for_pixels(margin_fixed)) / bytes_per_screen;
+    if max_area == 0 {
+        return Err(anyhow!("Memory budget is too small to draw a single screen"));
+    }
+    
+    Ok(max_area)
+}
+
+fn overview_size_in_bytes(bounds: &Bounds, scale: OverviewScale) -> u64 {
+    let (screen_width, screen_height) = scale.screen_size();
+    bounds.width() * screen_width as u64 * bounds.height() * screen_height as u64 * 4
+}
+
+/// Parses the seed given on the command line, or picks a random one if there isn't one
+fn parse_seed(seed: Option<String>) -> Result<MapSeed> {
+    match seed.map(MapSeed::try_from) {
+        Some(Ok(seed)) => Ok(seed),
+        Some(Err(err)) => {
+            eprintln!("Failed to parse seed. The seed must be 1-16 hex digits (0-9 A-F).");
+            Err(err.into())
+        },
+        None => Ok(MapSeed::random()),
+    }
+}
+
+/// The level's directory, given either the directory itself or a file inside it
+fn find_level_dir(level: &Path) -> PathBuf {
+    if level.is_dir() {
+        level.to_owned()
+    }
+    else {
+        level.parent()
+            .unwrap_or("".as_ref())
+            .to_owned()
+    }
+}
+
+/// The partition's name if it has one, otherwise its bounds
+fn partition_label(partition: &Partition) -> String {
+    match partition.name() {
+        Some(name) => name.to_owned(),
+        None => partition.bounds().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN_BYTES: u64 = SCREEN_SIZE.0 as u64 * SCREEN_SIZE.1 as u64 * 4;
+
+    /// The memory needed to draw and encode a `width` x `height` image with `margin`
+    fn image_bytes(width: u64, height: u64, margin: (u32, u32)) -> u64 {
+        let pixels = (width * SCREEN_SIZE.0 as u64 + 2 * margin.0 as u64) * (height * SCREEN_SIZE.1 as u64 + 2 * margin.1 as u64);
+        (pixels as f64 * 4.0 * (1.0 + ENCODER_OVERHEAD)) as u64
+    }
+
+    #[test]
+    fn budget_without_margin_or_overview() {
+        let budget = ASSET_CACHE_RESERVE + 10 * SCREEN_BYTES * 3 / 2;
+        assert_eq!(max_area_for_budget(budget, (0, 0), 0).unwrap(), 10);
+        assert_eq!(max_area_for_budget(budget - 1, (0, 0), 0).unwrap(), 9);
+    }
+
+    #[test]
+    fn any_image_shape_fits_the_budget() {
+        for margin in [(0, 0), (24, 0), (0, 24), (100, 37), (600, 240), (1000, 500)] {
+            for budget in [300 << 20, 512 << 20, 1 << 30] {
+                let overview_bytes = 16 << 20;
+                let canvas_budget = budget - ASSET_CACHE_RESERVE - overview_bytes;
+                let max_area = max_area_for_budget(budget, margin, overview_bytes).unwrap();
+
+                for width in 1..=max_area {
+                    let height = max_area / width;
+                    assert!(
+                        image_bytes(width, height, margin) <= canvas_budget,
+                        "{width}x{height} with margin {margin:?} is over {budget}",
+                    );
+                }
+                // Making the image any larger should usually go over
+                if margin == (0, 0) {
+                    assert!(image_bytes(max_area + 1, 1, margin) > canvas_budget);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn overview_takes_from_the_budget() {
+        let bounds = Bounds { x: 0..10, y: 0..4 };
+        assert_eq!(overview_size_in_bytes(&bounds, OverviewScale::Tile), 250 * 40 * 4);
+        assert_eq!(overview_size_in_bytes(&bounds, OverviewScale::Screen), 10 * 4 * 4);
+
+        let budget = ASSET_CACHE_RESERVE + 10 * SCREEN_BYTES * 3 / 2;
+        assert_eq!(max_area_for_budget(budget, (0, 0), SCREEN_BYTES * 3).unwrap(), 8);
+        assert!(max_area_for_budget(budget, (0, 0), budget).is_err());
+    }
 }
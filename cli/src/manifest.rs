@@ -0,0 +1,41 @@
+use ksmap::{drawing::SCREEN_SIZE, partition::Bounds};
+use serde::Serialize;
+
+/// Describes where each image of a partitioned map belongs so they can be stitched back together
+#[derive(Serialize)]
+pub struct Manifest {
+    pub seed: String,
+    pub images: Vec<ManifestImage>,
+}
+
+#[derive(Serialize)]
+pub struct ManifestImage {
+    pub file: String,
+    /// The screens the image belongs to, as half-open ranges
+    pub screens_x: [i64; 2],
+    pub screens_y: [i64; 2],
+    pub width: u32,
+    pub height: u32,
+    /// The world position of the image's top left corner in pixels, where x0y0 starts at (0, 0)
+    pub world_x: i64,
+    pub world_y: i64,
+    /// The size of the context margin drawn on each side of the image in pixels
+    pub margin_x: u32,
+    pub margin_y: u32,
+}
+
+impl ManifestImage {
+    pub fn new(file: String, bounds: &Bounds, size: (u32, u32), margin: (u32, u32)) -> Self {
+        Self {
+            file,
+            screens_x: [bounds.x.start, bounds.x.end],
+            screens_y: [bounds.y.start, bounds.y.end],
+            width: size.0,
+            height: size.1,
+            world_x: bounds.x.start * SCREEN_SIZE.0 as i64 - margin.0 as i64,
+            world_y: bounds.y.start * SCREEN_SIZE.1 as i64 - margin.1 as i64,
+            margin_x: margin.0,
+            margin_y: margin.1,
+        }
+    }
+}
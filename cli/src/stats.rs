@@ -0,0 +1,76 @@
+use anyhow::Result;
+use ksmap::analysis::{self, LevelStats};
+use libks::{map_bin, world_ini};
+
+use crate::cli::{OutputFormat, StatsArgs};
+
+pub fn run(args: &StatsArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))?;
+    let ini = world_ini::load_ini_from_dir(&level_dir)?;
+
+    let stats = analysis::level_stats(&screens, &ini);
+    match args.format {
+        OutputFormat::Text => print_text(&stats),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+    }
+
+    Ok(())
+}
+
+fn print_text(stats: &LevelStats) {
+    println!("Screens:      {}", stats.screens);
+    println!("Bounds:       {} ({}x{})", stats.bounds, stats.bounds.width(), stats.bounds.height());
+    println!("Unfilled:     {:.1}% of the bounds", stats.unfilled_bounds_ratio * 100.0);
+    println!("Empty:        {} ({:.1}%)", stats.empty_screens, stats.empty_screen_ratio * 100.0);
+    println!("Objects:      {}", stats.objects);
+
+    println!();
+    println!("Objects per bank:");
+    for (bank, count) in &stats.objects_per_bank {
+        println!("  {bank:>3}: {count}");
+    }
+
+    println!();
+    println!("Objects:");
+    for object in &stats.object_counts {
+        println!("  {:>8}: {}", object.id, object.count);
+    }
+
+    if !stats.custom_objects.is_empty() {
+        println!();
+        println!("Custom objects:");
+        for object in &stats.custom_objects {
+            let image = object.image.as_deref().unwrap_or("(no image)");
+            println!("  {:>8}: {} ({image})", object.id, object.count);
+        }
+    }
+
+    println!();
+    println!("Screens per tileset:");
+    for (tileset, count) in &stats.tilesets {
+        println!("  {tileset:>3}: {count}");
+    }
+
+    println!();
+    println!("Screens per gradient:");
+    for (gradient, count) in &stats.gradients {
+        println!("  {gradient:>3}: {count}");
+    }
+
+    println!();
+    println!("Screen assets:");
+    for assets in &stats.screen_assets {
+        let pos = format!("x{}y{}", assets.position.0, assets.position.1);
+        println!(
+            "  {pos:>12}: tilesets {:>3} {:>3}, gradient {:>3}",
+            assets.tileset_a, assets.tileset_b, assets.gradient,
+        );
+    }
+
+    println!();
+    println!("Overlay screens: {}", stats.overlay_screens.len());
+    for pos in &stats.overlay_screens {
+        println!("  x{}y{}", pos.0, pos.1);
+    }
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use ksmap::analysis::{self, TilesetUsage};
+use ksmap::definitions::ObjectDefs;
+use ksmap::drawing;
+use ksmap::graphics::Graphics;
+use libks::map_bin;
+
+use crate::cli::{OutputFormat, TilesetsArgs};
+
+pub fn run(args: &TilesetsArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))?;
+
+    let usages = analysis::tileset_usage(&screens);
+    match args.format {
+        OutputFormat::Text => print_text(&usages),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&usages)?),
+    }
+
+    if let Some(heatmap_dir) = &args.heatmaps {
+        let data_dir = args.data_dir.clone().unwrap_or_else(|| level_dir.join("../../Data"));
+        write_heatmaps(&usages, &data_dir, &level_dir, heatmap_dir)?;
+    }
+
+    Ok(())
+}
+
+fn write_heatmaps(usages: &[TilesetUsage], data_dir: &Path, level_dir: &Path, heatmap_dir: &Path) -> Result<()> {
+    // Tilesets don't depend on object definitions
+    let defs = ObjectDefs::default();
+    let mut gfx = Graphics::new(data_dir, level_dir, "", &defs);
+    let ids: Vec<_> = usages.iter()
+        .map(|usage| usage.tileset)
+        .collect();
+    gfx.load_tilesets(&ids)?;
+
+    fs::create_dir_all(heatmap_dir)?;
+    for usage in usages {
+        let Some(tileset) = gfx.tileset(usage.tileset) else {
+            eprintln!("Tileset{}.png not found, skipping its heatmap", usage.tileset);
+            continue;
+        };
+
+        let heatmap = drawing::draw_tileset_heatmap(tileset, usage);
+        drawing::export_canvas(heatmap, &heatmap_dir.join(format!("Tileset{}_usage.png", usage.tileset)))?;
+    }
+
+    Ok(())
+}
+
+fn print_text(usages: &[TilesetUsage]) {
+    for usage in usages {
+        let placements: usize = usage.tiles.iter()
+            .map(|tile| tile.count)
+            .sum();
+        println!(
+            "Tileset {}: {} of 127 tiles used, {placements} placements",
+            usage.tileset,
+            usage.tiles.len(),
+        );
+
+        for tile in &usage.tiles {
+            let screens: Vec<_> = tile.screens.iter()
+                .map(|pos| format!("x{}y{}", pos.0, pos.1))
+                .collect();
+            println!(
+                "  {:>3} ({:>2}, {}): {:>6} on {} screen(s): {}",
+                tile.index,
+                tile.index % 16,
+                tile.index / 16,
+                tile.count,
+                screens.len(),
+                screens.join(" "),
+            );
+        }
+
+        let unused: Vec<_> = (1..128u8)
+            .filter(|index| !usage.tiles.iter().any(|tile| tile.index == *index))
+            .map(|index| index.to_string())
+            .collect();
+        if !unused.is_empty() {
+            println!("  Unused: {}", unused.join(" "));
+        }
+        println!();
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use ksmap::analysis::{self, LevelFile};
+use ksmap::definitions;
+use libks::{map_bin, world_ini};
+use serde_json::json;
+
+use crate::cli::{OutputFormat, UnusedArgs};
+
+pub fn run(args: &UnusedArgs) -> Result<()> {
+    let level_dir = crate::find_level_dir(&args.level);
+    let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))?;
+    let ini = world_ini::load_ini_from_dir(&level_dir)?;
+    let mut defs = definitions::load_object_defs(&args.object_definitions)?;
+    definitions::insert_custom_obj_defs(&mut defs, &ini);
+
+    let assets = analysis::list_assets(&screens, &defs);
+    let files = analysis::find_unused_files(&assets, &defs, &level_dir);
+    let total_size: u64 = files.iter()
+        .map(|file| file.size)
+        .sum();
+
+    match args.format {
+        OutputFormat::Text => print_text(&files, total_size),
+        OutputFormat::Json => {
+            let output = json!({
+                "files": files,
+                "total_size": total_size,
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        },
+    }
+
+    Ok(())
+}
+
+fn print_text(files: &[LevelFile], total_size: u64) {
+    if files.is_empty() {
+        println!("No unused files found");
+        return;
+    }
+
+    for file in files {
+        println!("{:>10}  {}", format_size(file.size), file.path.display());
+    }
+    println!("{:>10}  total in {} file(s)", format_size(total_size), files.len());
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
@@ -1,9 +1,19 @@
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
 
-use libks::map_bin::{AssetId, LayerData, ScreenData};
+use libks::{ScreenCoord, constants::SCREEN_WIDTH, map_bin::{AssetId, LayerData, ScreenData, Tile}};
+use libks_ini::Ini;
+use petgraph::unionfind::UnionFind;
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use serde::{Deserialize, Serialize};
 
-use crate::definitions::{ObjectDefs, ObjectKind};
+use crate::definitions::{ObjectCategory, ObjectDefs, ObjectKind};
 use crate::id::ObjectId;
+use crate::partition::Bounds;
 
 pub struct AssetsUsed {
     pub tilesets: Vec<AssetId>,
@@ -14,7 +24,10 @@ pub struct AssetsUsed {
 pub fn list_assets(screens: &[ScreenData], defs: &ObjectDefs) -> AssetsUsed {
     let mut tilesets_seen = [false; 256];
     let mut gradients_seen = [false; 256];
-    let mut objects_seen = HashSet::<ObjectId>::new();
+    let mut objects_seen: HashSet<ObjectId> = count_objects(screens)
+        .into_keys()
+        .map(ObjectId::from)
+        .collect();
     
     for screen in screens {
         let mut uses_tileset_a = false;
@@ -27,14 +40,6 @@ pub fn list_assets(screens: &[ScreenData], defs: &ObjectDefs) -> AssetsUsed {
             }
         }
         
-        for LayerData(layer) in &screen.layers[4..] {
-            for tile in layer {
-                if tile.1 == 0 { continue }
-                let id = ObjectId::from(tile);
-                objects_seen.insert(id.clone());
-            }
-        }
-        
         tilesets_seen[screen.assets.tileset_a as usize] |= uses_tileset_a;
         tilesets_seen[screen.assets.tileset_b as usize] |= uses_tileset_b;
         gradients_seen[screen.assets.gradient as usize] = true;
@@ -105,3 +110,543 @@ pub fn count_laser_phases(screens: &[ScreenData], defs: &ObjectDefs) -> Vec<[usi
     
     counts
 }
+
+/// Counts how many times each object appears on the object layers of all screens
+pub fn count_objects(screens: &[ScreenData]) -> FxHashMap<Tile, usize> {
+    let mut counts = FxHashMap::default();
+    
+    for screen in screens {
+        for LayerData(layer) in &screen.layers[4..] {
+            for tile in layer {
+                if tile.1 == 0 { continue }
+                *counts.entry(*tile).or_insert(0) += 1;
+            }
+        }
+    }
+    
+    counts
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelStats {
+    pub screens: usize,
+    pub bounds: Bounds,
+    /// The fraction of the bounding box that has no screen in it
+    pub unfilled_bounds_ratio: f64,
+    /// The number of screens with nothing on any layer
+    pub empty_screens: usize,
+    /// The fraction of screens with nothing on any layer
+    pub empty_screen_ratio: f64,
+    pub objects: usize,
+    pub objects_per_bank: BTreeMap<u8, usize>,
+    pub object_counts: Vec<ObjectCount>,
+    pub custom_objects: Vec<CustomObjectUse>,
+    /// The number of screens that draw tiles from each tileset
+    pub tilesets: BTreeMap<AssetId, usize>,
+    /// The number of screens that use each gradient
+    pub gradients: BTreeMap<AssetId, usize>,
+    pub overlay_screens: Vec<ScreenCoord>,
+    pub screen_assets: Vec<ScreenAssets>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectCount {
+    pub id: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomObjectUse {
+    pub id: String,
+    pub image: Option<String>,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenAssets {
+    pub position: ScreenCoord,
+    pub tileset_a: AssetId,
+    pub tileset_b: AssetId,
+    pub gradient: AssetId,
+}
+
+pub fn level_stats(screens: &[ScreenData], ini: &Ini) -> LevelStats {
+    let bounds = Bounds::from_iter(screens.iter().map(|screen| &screen.position));
+    let bounds_area = bounds.width() * bounds.height();
+    let unfilled_bounds_ratio = if bounds_area == 0 {
+            0.0
+        }
+        else {
+            1.0 - screens.len() as f64 / bounds_area as f64
+        };
+    
+    let mut counts: Vec<_> = count_objects(screens)
+        .into_iter()
+        .collect();
+    counts.sort_by_key(|(tile, _)| (tile.0, tile.1));
+    
+    let mut objects_per_bank = BTreeMap::new();
+    let mut custom_objects = Vec::new();
+    for (tile, count) in &counts {
+        *objects_per_bank.entry(tile.0).or_insert(0) += count;
+        
+        let section_name = match tile.0 {
+            254 => format!("Custom Object B{}", tile.1),
+            255 => format!("Custom Object {}", tile.1),
+            _ => continue,
+        };
+        let image = ini.section(&section_name)
+            .and_then(|section| section.get("Image"))
+            .map(str::to_owned);
+        custom_objects.push(CustomObjectUse {
+            id: ObjectId::from(tile).to_string(),
+            image,
+            count: *count,
+        });
+    }
+    
+    let mut tilesets = BTreeMap::new();
+    let mut gradients = BTreeMap::new();
+    let mut overlay_screens = Vec::new();
+    let mut screen_assets = Vec::with_capacity(screens.len());
+    let mut empty_screens = 0;
+    for screen in screens {
+        // Index 0 is nothing on both tile and object layers, whatever the tileset or bank
+        let is_empty = screen.layers.iter()
+            .all(|LayerData(layer)| layer.iter().all(|tile| tile.1 == 0));
+        if is_empty {
+            empty_screens += 1;
+        }
+        
+        let mut uses_tileset = [false; 2];
+        for LayerData(layer) in &screen.layers[..4] {
+            for tile in layer {
+                if tile.1 > 0 && tile.0 < 2 {
+                    uses_tileset[tile.0 as usize] = true;
+                }
+            }
+        }
+        
+        if uses_tileset[0] {
+            *tilesets.entry(screen.assets.tileset_a).or_insert(0) += 1;
+        }
+        if uses_tileset[1] && (screen.assets.tileset_b != screen.assets.tileset_a || !uses_tileset[0]) {
+            *tilesets.entry(screen.assets.tileset_b).or_insert(0) += 1;
+        }
+        *gradients.entry(screen.assets.gradient).or_insert(0) += 1;
+        
+        let is_overlay = ini.section(&format!("x{}y{}", screen.position.0, screen.position.1))
+            .and_then(|section| section.get("Overlay"))
+            .is_some_and(|v| v.eq_ignore_ascii_case("True"));
+        if is_overlay {
+            overlay_screens.push(screen.position);
+        }
+        
+        screen_assets.push(ScreenAssets {
+            position: screen.position,
+            tileset_a: screen.assets.tileset_a,
+            tileset_b: screen.assets.tileset_b,
+            gradient: screen.assets.gradient,
+        });
+    }
+    
+    let empty_screen_ratio = if screens.is_empty() {
+            0.0
+        }
+        else {
+            empty_screens as f64 / screens.len() as f64
+        };
+    
+    LevelStats {
+        screens: screens.len(),
+        bounds,
+        unfilled_bounds_ratio,
+        empty_screens,
+        empty_screen_ratio,
+        objects: counts.iter().map(|(_, count)| count).sum(),
+        objects_per_bank,
+        object_counts: counts.into_iter()
+            .map(|(tile, count)| ObjectCount {
+                id: ObjectId::from(tile).to_string(),
+                count,
+            })
+            .collect(),
+        custom_objects,
+        tilesets,
+        gradients,
+        overlay_screens,
+        screen_assets,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CollectableKind {
+    Power,
+    Key,
+    Creature,
+    Coin,
+    Artifact,
+    /// An object in the `Collectable` category that isn't one of the kinds above
+    Other,
+}
+
+impl CollectableKind {
+    /// The kind of collectable an object is according to its definition, if any
+    pub fn of(tile: Tile, defs: &ObjectDefs) -> Option<Self> {
+        let def = defs.get(&ObjectId::from(tile))?;
+        match (def.collectable, def.category) {
+            (Some(kind), _) => Some(kind),
+            (None, Some(ObjectCategory::Collectable)) => Some(CollectableKind::Other),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Collectable {
+    pub kind: CollectableKind,
+    /// The object placed in the map
+    pub object: String,
+    /// The object that a custom object overrides
+    pub original: Option<String>,
+    pub screen: ScreenCoord,
+    pub layer: usize,
+    /// The index of the tile in the layer, counting left to right and top to bottom
+    pub tile: usize,
+}
+
+impl Collectable {
+    /// The column and row of the tile within the screen
+    pub fn tile_position(&self) -> (usize, usize) {
+        (self.tile % SCREEN_WIDTH, self.tile / SCREEN_WIDTH)
+    }
+}
+
+/// Lists every collectable in the level, including custom objects that override one, ordered by
+/// screen from top to bottom and left to right, then by layer and tile
+pub fn list_collectables(screens: &[ScreenData], defs: &ObjectDefs) -> Vec<Collectable> {
+    let mut collectables = Vec::new();
+    
+    for screen in screens {
+        for (index_layer, LayerData(layer)) in screen.layers.iter().enumerate().skip(4) {
+            for (index_tile, tile) in layer.iter().enumerate() {
+                if tile.1 == 0 { continue }
+                
+                let original = match defs.get(&ObjectId::from(tile)).map(|def| def.kind) {
+                    Some(ObjectKind::OverrideObject(original)) => Some(original),
+                    _ => None,
+                };
+                let Some(kind) = CollectableKind::of(original.unwrap_or(*tile), defs) else { continue };
+                
+                collectables.push(Collectable {
+                    kind,
+                    object: ObjectId::from(tile).to_string(),
+                    original: original.map(|original| ObjectId::from(original).to_string()),
+                    screen: screen.position,
+                    layer: index_layer,
+                    tile: index_tile,
+                });
+            }
+        }
+    }
+    
+    collectables.sort_by_key(|collectable| (
+        collectable.screen.1,
+        collectable.screen.0,
+        collectable.layer,
+        collectable.tile,
+    ));
+    collectables
+}
+
+/// A file in a level's directory
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelFile {
+    /// The path of the file relative to the level's directory
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Lists the files in the level's Tilesets, Gradients and Custom Objects directories that the level
+/// doesn't use. `defs` must already include the level's custom objects, and `assets` must come from
+/// the same definitions. Images set for graphics overrides in World.ini always count as used.
+pub fn find_unused_files(assets: &AssetsUsed, defs: &ObjectDefs, level_dir: &Path) -> Vec<LevelFile> {
+    let mut used = FxHashSet::default();
+    for tileset in &assets.tilesets {
+        used.insert(format!("tilesets/tileset{tileset}.png"));
+    }
+    for gradient in &assets.gradients {
+        used.insert(format!("gradients/gradient{gradient}.png"));
+    }
+    
+    let custom_images = assets.objects.iter()
+        .filter_map(|id| defs.get(id))
+        .filter(|def| !matches!(def.kind, ObjectKind::Object))
+        .chain(defs.values().filter(|def| def.is_overridden))
+        .filter_map(|def| def.path.as_deref());
+    for path in custom_images {
+        used.insert(file_key(&Path::new("Custom Objects").join(path.replace('\\', "/"))));
+    }
+    
+    let mut files = Vec::new();
+    for dir in ["Tilesets", "Gradients", "Custom Objects"] {
+        list_files_recursive(&level_dir.join(dir), Path::new(dir), &mut files);
+    }
+    
+    let mut unused: Vec<_> = files.into_iter()
+        .filter(|file| !used.contains(&file_key(&file.path)))
+        .collect();
+    unused.sort_by(|a, b| a.path.cmp(&b.path));
+    unused
+}
+
+/// Adds every file under `dir` to `files`, with paths relative to `dir` joined onto `relative`
+pub(crate) fn list_files_recursive(dir: &Path, relative: &Path, files: &mut Vec<LevelFile>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let Ok(metadata) = entry.metadata() else { continue };
+        let path = relative.join(entry.file_name());
+        if metadata.is_dir() {
+            list_files_recursive(&entry.path(), &path, files);
+        }
+        else {
+            files.push(LevelFile {
+                path,
+                size: metadata.len(),
+            });
+        }
+    }
+}
+
+/// A relative path in lowercase with `/` between components, for matching paths from World.ini.
+/// KS runs on Windows, so paths in World.ini don't have to match case or use the same separator.
+pub(crate) fn file_key(path: &Path) -> String {
+    path.iter()
+        .map(|component| component.to_string_lossy().to_ascii_lowercase())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// How often each tile of a tileset is used on the tile layers, with the screens it's used on
+#[derive(Debug, Clone, Serialize)]
+pub struct TilesetUsage {
+    pub tileset: AssetId,
+    /// The tiles that are used at least once, ordered by index
+    pub tiles: Vec<TileUsage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TileUsage {
+    /// The position of the tile in the tileset, counting left to right and top to bottom
+    pub index: u8,
+    pub count: usize,
+    /// The screens the tile is used on, from top to bottom and left to right
+    pub screens: Vec<ScreenCoord>,
+}
+
+/// Counts the tiles used from each tileset, ordered by tileset ID. Index 0 is the empty tile and
+/// is never counted.
+pub fn tileset_usage(screens: &[ScreenData]) -> Vec<TilesetUsage> {
+    let mut usages = BTreeMap::<AssetId, BTreeMap<u8, TileUsage>>::new();
+    
+    let mut screens: Vec<_> = screens.iter().collect();
+    screens.sort_by_key(|screen| (screen.position.1, screen.position.0));
+    for screen in screens {
+        for LayerData(layer) in &screen.layers[..4] {
+            for tile in layer {
+                if tile.1 == 0 { continue }
+                
+                let tileset = match tile.0 {
+                    0 => screen.assets.tileset_a,
+                    1 => screen.assets.tileset_b,
+                    _ => continue,
+                };
+                let usage = usages.entry(tileset)
+                    .or_default()
+                    .entry(tile.1)
+                    .or_insert_with(|| TileUsage {
+                        index: tile.1,
+                        count: 0,
+                        screens: Vec::new(),
+                    });
+                
+                usage.count += 1;
+                if usage.screens.last() != Some(&screen.position) {
+                    usage.screens.push(screen.position);
+                }
+            }
+        }
+    }
+    
+    usages.into_iter()
+        .map(|(tileset, tiles)| TilesetUsage {
+            tileset,
+            tiles: tiles.into_values().collect(),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateScreens {
+    /// Groups of screens with the same tiles, objects and assets
+    pub exact: Vec<Vec<ScreenCoord>>,
+    /// Groups of screens where each screen differs from another in the group by at most the
+    /// maximum difference, but not all screens are exact duplicates
+    pub near: Vec<ScreenCluster>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenCluster {
+    pub screens: Vec<ScreenCoord>,
+    /// The largest difference between two screens that put the group together
+    pub max_difference: usize,
+}
+
+/// Finds screens that are exact or near duplicates of each other. The difference between two
+/// screens is the number of tiles that differ on any layer, plus the number of assets that differ.
+/// Groups and the screens in them are ordered from top to bottom and left to right.
+pub fn find_duplicate_screens(screens: &[ScreenData], max_difference: usize) -> DuplicateScreens {
+    let mut screens: Vec<_> = screens.iter().collect();
+    screens.sort_by_key(|screen| (screen.position.1, screen.position.0));
+    
+    let groups = group_exact_duplicates(&screens, screen_hash, |a, b| screen_difference(a, b, 0).is_some());
+    let exact = groups.iter()
+        .filter(|group| group.len() > 1)
+        .map(|group| group.iter().map(|screen| screen.position).collect())
+        .collect();
+    
+    // Compare one screen from each group. Screens can only be within the maximum difference if
+    // their numbers of non-empty tiles are, so only nearby screens in that order need comparing.
+    let mut near = Vec::new();
+    if max_difference > 0 {
+        let mut order: Vec<_> = (0..groups.len())
+            .map(|i| (count_tiles(groups[i][0]), i))
+            .collect();
+        order.sort();
+        
+        let mut uf = UnionFind::<usize>::new(groups.len());
+        let mut differences = vec![0; groups.len()];
+        for (start, (tiles_a, index_a)) in order.iter().enumerate() {
+            for (tiles_b, index_b) in &order[start + 1..] {
+                if tiles_b - tiles_a > max_difference {
+                    break;
+                }
+                
+                let Some(difference) = screen_difference(groups[*index_a][0], groups[*index_b][0], max_difference) else { continue };
+                uf.union(*index_a, *index_b);
+                differences[*index_a] = differences[*index_a].max(difference);
+                differences[*index_b] = differences[*index_b].max(difference);
+            }
+        }
+        
+        let mut clusters = BTreeMap::<usize, Vec<usize>>::new();
+        for (index_group, index_rep) in uf.into_labeling().into_iter().enumerate() {
+            clusters.entry(index_rep).or_default().push(index_group);
+        }
+        let mut clusters: Vec<_> = clusters.into_values()
+            .filter(|members| members.len() > 1)
+            .collect();
+        clusters.sort_by_key(|members| members[0]);
+        
+        for members in clusters {
+            let mut positions: Vec<_> = members.iter()
+                .flat_map(|index_group| groups[*index_group].iter().map(|screen| screen.position))
+                .collect();
+            positions.sort_by_key(|pos| (pos.1, pos.0));
+            near.push(ScreenCluster {
+                screens: positions,
+                max_difference: members.iter()
+                    .map(|index_group| differences[*index_group])
+                    .max()
+                    .unwrap_or(0),
+            });
+        }
+    }
+    
+    DuplicateScreens {
+        exact,
+        near,
+    }
+}
+
+/// Groups items that are exactly the same, using `is_same` to tell hash collisions apart. Groups
+/// are ordered by their first item, and keep the order of `items` within them.
+fn group_exact_duplicates<T: Copy>(items: &[T], hash: impl Fn(T) -> u64, is_same: impl Fn(T, T) -> bool) -> Vec<Vec<T>> {
+    let mut groups = Vec::<Vec<T>>::new();
+    let mut by_hash = FxHashMap::<u64, Vec<usize>>::default();
+    for item in items {
+        let candidates = by_hash.entry(hash(*item)).or_default();
+        match candidates.iter().find(|index| is_same(groups[**index][0], *item)) {
+            Some(index) => groups[*index].push(*item),
+            None => {
+                candidates.push(groups.len());
+                groups.push(vec![*item]);
+            },
+        }
+    }
+    groups
+}
+
+fn screen_assets(screen: &ScreenData) -> [AssetId; 6] {
+    [
+        screen.assets.tileset_a,
+        screen.assets.tileset_b,
+        screen.assets.atmosphere_a,
+        screen.assets.atmosphere_b,
+        screen.assets.music,
+        screen.assets.gradient,
+    ]
+}
+
+fn screen_hash(screen: &ScreenData) -> u64 {
+    let mut hasher = FxHasher::default();
+    for LayerData(layer) in &screen.layers {
+        layer.hash(&mut hasher);
+    }
+    screen_assets(screen).hash(&mut hasher);
+    hasher.finish()
+}
+
+pub(crate) fn count_tiles(screen: &ScreenData) -> usize {
+    screen.layers.iter()
+        .flat_map(|LayerData(layer)| layer)
+        .filter(|tile| tile.1 != 0)
+        .count()
+}
+
+/// The number of tiles and assets that differ between two screens, or `None` if it's more than
+/// `max_difference`
+pub(crate) fn screen_difference(a: &ScreenData, b: &ScreenData, max_difference: usize) -> Option<usize> {
+    let mut difference = screen_assets(a).iter()
+        .zip(screen_assets(b))
+        .filter(|(asset_a, asset_b)| **asset_a != *asset_b)
+        .count();
+    if difference > max_difference {
+        return None;
+    }
+    
+    for (LayerData(layer_a), LayerData(layer_b)) in a.layers.iter().zip(&b.layers) {
+        for (tile_a, tile_b) in layer_a.iter().zip(layer_b) {
+            if tile_a != tile_b {
+                difference += 1;
+                if difference > max_difference {
+                    return None;
+                }
+            }
+        }
+    }
+    
+    Some(difference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_collisions_are_not_duplicates() {
+        let groups = group_exact_duplicates(&[1, 2, 1, 3, 2], |_| 0, |a, b| a == b);
+        assert_eq!(groups, vec![vec![1, 1], vec![2, 2], vec![3]]);
+    }
+}
@@ -0,0 +1,206 @@
+use std::{fmt::{self, Display}, path::Path};
+
+use libks::ScreenCoord;
+use libks_ini::Ini;
+use rustc_hash::FxHashSet;
+use serde::Serialize;
+
+use crate::{
+    analysis,
+    definitions::{self, ObjectDefs},
+    links,
+    screen_map::ScreenMap,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Severity {
+    /// Harmless leftovers, e.g. settings for a screen that was deleted
+    Info,
+    /// Something that probably doesn't look or work as intended
+    Warning,
+    /// Something that is broken in game
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => f.write_str("info"),
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    pub severity: Severity,
+    /// The screen the problem was found on, if it belongs to one
+    pub screen: Option<ScreenCoord>,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(severity: Severity, screen: Option<ScreenCoord>, message: String) -> Self {
+        Self {
+            severity,
+            screen,
+            message,
+        }
+    }
+}
+
+/// Cross-references the map, the screen and custom object sections of World.ini and the files in
+/// the level directory. Problems are sorted from most to least severe.
+pub fn check_level(screens: &ScreenMap, ini: &Ini, defs: &ObjectDefs, level_dir: &Path) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    check_links(screens, ini, &mut problems);
+    check_screen_sections(screens, ini, &mut problems);
+    check_custom_objects(screens, ini, level_dir, &mut problems);
+    check_graphics_overrides(ini, defs, level_dir, &mut problems);
+
+    problems.sort_by(|a, b| b.severity.cmp(&a.severity)
+        .then_with(|| a.screen.map(|pos| (pos.1, pos.0)).cmp(&b.screen.map(|pos| (pos.1, pos.0)))));
+    problems
+}
+
+/// Shifts and warps that lead to screens that aren't in the map
+fn check_links(screens: &ScreenMap, ini: &Ini, problems: &mut Vec<Problem>) {
+    for link in links::find_screen_links(screens, ini) {
+        if screens.pos(&link.to).is_some() {
+            continue;
+        }
+
+        problems.push(Problem::new(
+            Severity::Error,
+            Some(link.from),
+            format!("{} leads to x{}y{}, which doesn't exist", link.kind, link.to.0, link.to.1),
+        ));
+    }
+}
+
+/// Screen sections for screens that aren't in the map
+fn check_screen_sections(screens: &ScreenMap, ini: &Ini, problems: &mut Vec<Problem>) {
+    for section in ini.iter_sections() {
+        let Some(pos) = parse_screen_key(section.key()) else { continue };
+        if screens.pos(&pos).is_some() {
+            continue;
+        }
+
+        problems.push(Problem::new(
+            Severity::Info,
+            Some(pos),
+            format!("[{}] is for a screen that doesn't exist", section.key()),
+        ));
+    }
+}
+
+/// Custom objects with missing images, and custom objects in the map that aren't defined
+fn check_custom_objects(screens: &ScreenMap, ini: &Ini, level_dir: &Path, problems: &mut Vec<Problem>) {
+    let files = list_files(&level_dir.join("Custom Objects"));
+    let counts = analysis::count_objects(screens);
+    let mut defined = FxHashSet::default();
+
+    for (tile, section) in definitions::custom_object_sections(ini) {
+        defined.insert(tile);
+
+        let severity = if counts.contains_key(&tile) {
+            Severity::Error
+        }
+        else {
+            Severity::Warning
+        };
+        match section.get("Image") {
+            Some(image) if !image.is_empty() => {
+                if !files.contains(&ini_path_key(image)) {
+                    problems.push(Problem::new(
+                        severity,
+                        None,
+                        format!("[{}] uses image {image:?}, which isn't in Custom Objects", section.key()),
+                    ));
+                }
+            },
+            _ => {
+                // Overrides of stock objects can keep the stock graphics
+                if section.get("Object").is_none() {
+                    problems.push(Problem::new(
+                        severity,
+                        None,
+                        format!("[{}] has no image", section.key()),
+                    ));
+                }
+            },
+        }
+    }
+
+    for screen in screens.iter() {
+        let mut reported = FxHashSet::default();
+        for layer in &screen.layers[4..] {
+            for tile in &layer.0 {
+                if tile.0 < 254 || tile.1 == 0 || defined.contains(tile) {
+                    continue;
+                }
+                if !reported.insert(*tile) {
+                    continue;
+                }
+
+                let section = match tile.0 {
+                    254 => format!("Custom Object B{}", tile.1),
+                    _ => format!("Custom Object {}", tile.1),
+                };
+                problems.push(Problem::new(
+                    Severity::Error,
+                    Some(screen.position),
+                    format!("Uses custom object {}-{}, but there's no [{section}]", tile.0, tile.1),
+                ));
+            }
+        }
+    }
+}
+
+/// Replacement graphics for powerups, coins and artifacts that are missing
+fn check_graphics_overrides(ini: &Ini, defs: &ObjectDefs, level_dir: &Path, problems: &mut Vec<Problem>) {
+    let Some(world_section) = ini.section("World") else { return };
+    let files = list_files(&level_dir.join("Custom Objects"));
+
+    let mut keys: Vec<_> = defs.values()
+        .filter_map(|def| def.override_key.as_deref())
+        .collect::<FxHashSet<_>>()
+        .into_iter()
+        .collect();
+    keys.sort();
+
+    for key in keys {
+        let Some(path) = world_section.get(key) else { continue };
+        if path.is_empty() || files.contains(&ini_path_key(path)) {
+            continue;
+        }
+
+        problems.push(Problem::new(
+            Severity::Error,
+            None,
+            format!("[World] {key} uses image {path:?}, which isn't in Custom Objects"),
+        ));
+    }
+}
+
+fn parse_screen_key(key: &str) -> Option<ScreenCoord> {
+    let (x, y) = key.strip_prefix(['x', 'X'])?
+        .split_once(['y', 'Y'])?;
+    Some((str::parse(x).ok()?, str::parse(y).ok()?))
+}
+
+/// The keys of the files under a directory, relative to it, to match against paths in World.ini
+fn list_files(dir: &Path) -> FxHashSet<String> {
+    let mut files = Vec::new();
+    analysis::list_files_recursive(dir, Path::new(""), &mut files);
+    files.iter()
+        .map(|file| analysis::file_key(&file.path))
+        .collect()
+}
+
+/// The key of a path from World.ini, to match against `list_files`
+fn ini_path_key(path: &str) -> String {
+    analysis::file_key(Path::new(&path.replace('\\', "/")))
+}
@@ -2,11 +2,12 @@ use std::{fs, ops::{Deref, DerefMut, Range, RangeInclusive}, path::Path};
 
 use anyhow::Result;
 use libks::map_bin::Tile;
-use libks_ini::Ini;
+use libks_ini::{Ini, VirtualSection};
 use rustc_hash::FxHashMap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    analysis::CollectableKind,
     drawing::BlendMode,
     id::{ObjectId, ObjectVariant},
 };
@@ -37,6 +38,10 @@ pub struct ObjectDef {
     pub override_frame_range: Option<Range<u32>>,
     #[serde(skip)]
     pub is_overridden: bool,
+    /// What the object does to the player, for the collision map
+    pub category: Option<ObjectCategory>,
+    /// The kind of collectable the object is, if it can be picked up
+    pub collectable: Option<CollectableKind>,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -71,6 +76,7 @@ pub enum AnimSync {
     None,
     Screen,
     Group,
+    World,
 }
 
 #[derive(Debug, Clone, Default, Deserialize)]
@@ -93,6 +99,16 @@ pub enum OffsetCombine {
     Replace,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum ObjectCategory {
+    /// Kills the player on touch
+    Hazard,
+    /// Can be picked up
+    Collectable,
+    /// Does something when the player touches or uses it, like shifts and triggers
+    Interactive,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
 pub enum OcoSupport {
     #[default]
@@ -106,23 +122,55 @@ pub enum OcoSupport {
 pub enum Limit {
     #[default]
     None,
-    First { n: usize },
-    Random { n: usize },
-    LogNPlusOne,
+    First {
+        n: usize,
+        #[serde(default)]
+        scope: LimitScope,
+    },
+    Random {
+        n: usize,
+        #[serde(default)]
+        scope: LimitScope,
+    },
+    LogNPlusOne {
+        #[serde(default)]
+        scope: LimitScope,
+    },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+/// The set of screens that share a limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
+pub enum LimitScope {
+    #[default]
+    Screen,
+    Group,
+    World,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
 pub enum LaserPhase {
     #[default]
     Red,
     Green,
 }
 
+#[derive(Default)]
 pub struct ObjectDefs {
     pub defs: FxHashMap<ObjectId, ObjectDef>,
     pub variants: FxHashMap<Tile, Vec<ObjectVariant>>,
 }
 
+impl Limit {
+    pub fn scope(&self) -> LimitScope {
+        match self {
+            Limit::None => LimitScope::Screen,
+            Limit::First { scope, .. }
+            | Limit::Random { scope, .. }
+            | Limit::LogNPlusOne { scope } => *scope,
+        }
+    }
+}
+
 impl ObjectDefs {
     pub fn variants_of(&self, object: Tile) -> &[ObjectVariant] {
         match self.variants.get(&object) {
@@ -130,6 +178,20 @@ impl ObjectDefs {
             None => &[],
         }
     }
+
+    /// Adds the definitions from `other`, replacing any for the same object and variant. Variants
+    /// are added to the ones already known for each object.
+    pub fn merge(&mut self, other: ObjectDefs) {
+        self.defs.extend(other.defs);
+        for (object, variants) in other.variants {
+            let known = self.variants.entry(object).or_default();
+            for variant in variants {
+                if !known.contains(&variant) {
+                    known.push(variant);
+                }
+            }
+        }
+    }
 }
 
 impl Deref for ObjectDefs {
@@ -158,7 +220,7 @@ pub fn load_object_defs(path: impl AsRef<Path>) -> Result<ObjectDefs> {
             let id = ObjectId::try_from(key)?;
             let def = table.try_into()?;
             
-            if id.1 != ObjectVariant::None {
+            if !id.1.is_none() {
                 variants.entry(id.0)
                     .or_insert(Vec::new())
                     .push(id.1);
@@ -174,25 +236,32 @@ pub fn load_object_defs(path: impl AsRef<Path>) -> Result<ObjectDefs> {
     })
 }
 
-pub fn insert_custom_obj_defs(defs: &mut ObjectDefs, ini: &Ini) {
-    for section in ini.iter_sections() {
-        let key_lower = section.key().to_ascii_lowercase();
+/// Loads the object definitions at `path`, then merges in the ones at `extra_path` if there are any
+pub fn load_object_defs_with_extra(path: impl AsRef<Path>, extra_path: Option<&Path>) -> Result<ObjectDefs> {
+    let mut defs = load_object_defs(path)?;
+    if let Some(extra_path) = extra_path {
+        defs.merge(load_object_defs(extra_path)?);
+    }
+    Ok(defs)
+}
 
-        let Some(suffix) = key_lower.strip_prefix("custom object ") else {
-            continue;
-        };
+/// The `[Custom Object N]` and `[Custom Object BN]` sections of World.ini with the tile each one
+/// defines
+pub(crate) fn custom_object_sections(ini: &Ini) -> impl Iterator<Item = (Tile, VirtualSection<'_>)> {
+    ini.iter_sections().filter_map(|section| {
+        let key_lower = section.key().to_ascii_lowercase();
+        let suffix = key_lower.strip_prefix("custom object ")?;
 
         let tile = match suffix.strip_prefix('b') {
-            Some(index) => {
-                let Ok(index) = str::parse::<u8>(index) else { continue };
-                Tile(254, index)
-            },
-            None => {
-                let Ok(index) = str::parse::<u8>(suffix) else { continue };
-                Tile(255, index)
-            },
+            Some(index) => Tile(254, str::parse::<u8>(index).ok()?),
+            None => Tile(255, str::parse::<u8>(suffix).ok()?),
         };
+        Some((tile, section))
+    })
+}
 
+pub fn insert_custom_obj_defs(defs: &mut ObjectDefs, ini: &Ini) {
+    for (tile, section) in custom_object_sections(ini) {
         let bank = section.get("Bank")
             .and_then(|v| str::parse(v).ok())
             .unwrap_or(0);
@@ -243,6 +312,7 @@ pub fn insert_custom_obj_defs(defs: &mut ObjectDefs, ini: &Ini) {
         let color_offsets = Vec::new();
         let mut replace_colors = Vec::new();
         let flip;
+        let category;
 
         if let Some(object) = object {
             let oco_id = ObjectId::from(Tile(bank, object));
@@ -293,6 +363,7 @@ pub fn insert_custom_obj_defs(defs: &mut ObjectDefs, ini: &Ini) {
                 limit = oco_def.limit;
                 alpha_range = oco_def.draw_params.alpha_range.clone();
                 flip = oco_def.draw_params.flip;
+                category = oco_def.category;
 
                 if let Some(offset) = oco_def.draw_params.offset {
                     match oco_def.offset_combine {
@@ -326,6 +397,7 @@ pub fn insert_custom_obj_defs(defs: &mut ObjectDefs, ini: &Ini) {
                 limit = Limit::None;
                 alpha_range = None;
                 flip = false;
+                category = None;
             }
         }
         else {
@@ -344,6 +416,7 @@ pub fn insert_custom_obj_defs(defs: &mut ObjectDefs, ini: &Ini) {
             limit = Limit::None;
             alpha_range = None;
             flip = false;
+            category = None;
         }
 
         let draw_params = DrawParams {
@@ -371,6 +444,8 @@ pub fn insert_custom_obj_defs(defs: &mut ObjectDefs, ini: &Ini) {
             override_key: None,
             override_frame_range: None,
             is_overridden: false,
+            category,
+            collectable: None,
         };
 
         defs.insert(ObjectId::from(tile), def);
@@ -403,3 +478,26 @@ fn unpack_color(mut color: i64) -> [u8; 3] {
 
     [r as u8, g as u8, b as u8]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_adds_variants_to_the_known_ones() {
+        let mut defs = ObjectDefs::default();
+        defs.variants.insert(Tile(0, 14), vec![ObjectVariant::SPOT, ObjectVariant::FLOOR]);
+        defs.insert(ObjectId::from(Tile(0, 14)), ObjectDef::default());
+
+        let mut extra = ObjectDefs::default();
+        extra.variants.insert(Tile(0, 14), vec![ObjectVariant::FLOOR, ObjectVariant::new("Tall")]);
+        extra.insert(ObjectId::from(Tile(0, 14)), ObjectDef {
+            editor_only: true,
+            ..Default::default()
+        });
+
+        defs.merge(extra);
+        assert_eq!(defs.variants_of(Tile(0, 14)), &[ObjectVariant::SPOT, ObjectVariant::FLOOR, ObjectVariant::new("Tall")]);
+        assert!(defs[&ObjectId::from(Tile(0, 14))].editor_only);
+    }
+}
@@ -0,0 +1,62 @@
+use libks::ScreenCoord;
+use serde::Serialize;
+
+use crate::{analysis, screen_map::ScreenMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScreenChange {
+    pub position: ScreenCoord,
+    pub kind: ChangeKind,
+    /// The number of tiles and assets that differ between the old and new screen. For added and
+    /// removed screens, this is the number of non-empty tiles.
+    pub difference: usize,
+}
+
+/// Compares the layers and assets of each screen in two versions of a level. Changes are ordered
+/// by screen from top to bottom and left to right.
+pub fn diff_screen_maps(old: &ScreenMap, new: &ScreenMap) -> Vec<ScreenChange> {
+    let mut changes = Vec::new();
+
+    for screen in old.iter() {
+        let change = match new.pos(&screen.position) {
+            Some(new_screen) => {
+                let difference = analysis::screen_difference(screen, new_screen, usize::MAX).unwrap_or(0);
+                if difference == 0 {
+                    continue;
+                }
+                ScreenChange {
+                    position: screen.position,
+                    kind: ChangeKind::Modified,
+                    difference,
+                }
+            },
+            None => ScreenChange {
+                position: screen.position,
+                kind: ChangeKind::Removed,
+                difference: analysis::count_tiles(screen),
+            },
+        };
+        changes.push(change);
+    }
+
+    for screen in new.iter() {
+        if old.pos(&screen.position).is_some() {
+            continue;
+        }
+        changes.push(ScreenChange {
+            position: screen.position,
+            kind: ChangeKind::Added,
+            difference: analysis::count_tiles(screen),
+        });
+    }
+
+    changes.sort_by_key(|change| (change.position.1, change.position.0));
+    changes
+}
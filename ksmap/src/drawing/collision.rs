@@ -0,0 +1,166 @@
+use image::{Rgba, RgbaImage};
+use libks::map_bin::{LayerData, ScreenData, Tile};
+
+use crate::{
+    definitions::{ObjectCategory, ObjectDef, ObjectDefs, ObjectKind},
+    graphics::Graphics,
+    id::ObjectId,
+};
+use super::{DrawOptions, SCREEN_SIZE, screen_index_to_pixels, text, tileset_index_to_pixels};
+
+const BACKGROUND_COLOR: Rgba<u8> = Rgba([24, 24, 32, 255]);
+/// Color of tiles on the layers behind the player, which don't block anything
+const SCENERY_COLOR: Rgba<u8> = Rgba([56, 56, 68, 255]);
+const SOLID_COLOR: Rgba<u8> = Rgba([220, 220, 220, 255]);
+const HAZARD_COLOR: Rgba<u8> = Rgba([255, 48, 48, 255]);
+const COLLECTABLE_COLOR: Rgba<u8> = Rgba([255, 220, 0, 255]);
+const INTERACTIVE_COLOR: Rgba<u8> = Rgba([64, 160, 255, 255]);
+/// Opacity of the fill inside object markers
+const MARKER_FILL_ALPHA: f32 = 0.5;
+
+/// Draws a schematic of where the player can stand. Tiles on layer 3 are drawn as solid masks of
+/// their pixels, the other tile layers are drawn faintly, and objects with a category are marked
+/// in the category's color.
+pub(super) fn draw_collision_screen(
+    screen: &ScreenData,
+    gfx: &Graphics,
+    defs: &ObjectDefs,
+    options: DrawOptions,
+) -> RgbaImage {
+    let mut image = RgbaImage::from_pixel(SCREEN_SIZE.0, SCREEN_SIZE.1, BACKGROUND_COLOR);
+    let tilesets = [gfx.tileset(screen.assets.tileset_a), gfx.tileset(screen.assets.tileset_b)];
+
+    for (index_layer, LayerData(layer)) in screen.layers[..4].iter().enumerate() {
+        let color = if index_layer == 3 { SOLID_COLOR } else { SCENERY_COLOR };
+        for (i, tile) in layer.iter().enumerate() {
+            if tile.1 == 0 { continue }
+            let Some(Some(tileset)) = tilesets.get(tile.0 as usize) else { continue };
+            draw_tile_mask(&mut image, tileset, tile.1, i as u8, color);
+        }
+    }
+
+    for LayerData(layer) in &screen.layers[4..] {
+        for (i, tile) in layer.iter().enumerate() {
+            if tile.1 == 0 { continue }
+
+            let def = defs.get(&ObjectId::from(tile));
+            if !options.editor_only && def.is_some_and(|def| def.editor_only) {
+                continue;
+            }
+            let Some(category) = category_of(defs, *tile) else { continue };
+            let color = match category {
+                ObjectCategory::Hazard => HAZARD_COLOR,
+                ObjectCategory::Collectable => COLLECTABLE_COLOR,
+                ObjectCategory::Interactive => INTERACTIVE_COLOR,
+            };
+            draw_marker(&mut image, i as u8, color);
+        }
+    }
+
+    image
+}
+
+/// The category of an object from its definition. Objects with a collectable kind are collectables,
+/// and custom objects that override another object fall back to the original's category.
+fn category_of(defs: &ObjectDefs, tile: Tile) -> Option<ObjectCategory> {
+    let def = defs.get(&ObjectId::from(tile));
+    let original = match def.map(|def| def.kind) {
+        Some(ObjectKind::OverrideObject(original)) => original,
+        _ => tile,
+    };
+    let category = |def: &ObjectDef| def.category
+        .or(def.collectable.map(|_| ObjectCategory::Collectable));
+
+    def.and_then(category)
+        .or_else(|| defs.get(&ObjectId::from(original)).and_then(category))
+}
+
+fn draw_tile_mask(image: &mut RgbaImage, tileset: &RgbaImage, index: u8, at_index: u8, color: Rgba<u8>) {
+    let (tile_x, tile_y) = tileset_index_to_pixels(index);
+    let (screen_x, screen_y) = screen_index_to_pixels(at_index);
+
+    for y in 0..24 {
+        for x in 0..24 {
+            if tile_x + x >= tileset.width() || tile_y + y >= tileset.height() {
+                continue;
+            }
+            if tileset.get_pixel(tile_x + x, tile_y + y).0[3] > 0 {
+                image.put_pixel(screen_x as u32 + x, screen_y as u32 + y, color);
+            }
+        }
+    }
+}
+
+fn draw_marker(image: &mut RgbaImage, at_index: u8, color: Rgba<u8>) {
+    let (x, y) = screen_index_to_pixels(at_index);
+    let (x, y) = (x as u32, y as u32);
+
+    for py in y + 1..y + 23 {
+        for px in x + 1..x + 23 {
+            let pixel = image.get_pixel_mut(px, py);
+            for (channel, tint) in pixel.0[..3].iter_mut().zip(color.0) {
+                *channel = (*channel as f32 * (1.0 - MARKER_FILL_ALPHA) + tint as f32 * MARKER_FILL_ALPHA) as u8;
+            }
+        }
+    }
+
+    text::fill_rect(image, x, y, 24, 1, color);
+    text::fill_rect(image, x, y + 23, 24, 1, color);
+    text::fill_rect(image, x, y, 1, 24, color);
+    text::fill_rect(image, x + 23, y, 1, 24, color);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::CollectableKind;
+    use super::*;
+
+    fn def(kind: ObjectKind, category: Option<ObjectCategory>) -> ObjectDef {
+        ObjectDef {
+            kind,
+            category,
+            ..Default::default()
+        }
+    }
+
+    fn test_defs() -> ObjectDefs {
+        let mut defs = ObjectDefs::default();
+        defs.insert(ObjectId::from(Tile(8, 12)), def(ObjectKind::Object, Some(ObjectCategory::Hazard)));
+        defs.insert(ObjectId::from(Tile(8, 13)), def(ObjectKind::Object, None));
+        defs.insert(ObjectId::from(Tile(0, 22)), ObjectDef {
+            collectable: Some(CollectableKind::Key),
+            ..Default::default()
+        });
+        defs.insert(ObjectId::from(Tile(8, 14)), def(ObjectKind::Object, Some(ObjectCategory::Collectable)));
+        // Overrides a hazard without a category of its own
+        defs.insert(ObjectId::from(Tile(255, 1)), def(ObjectKind::OverrideObject(Tile(8, 12)), None));
+        // Overrides a hazard, but is defined as something else
+        defs.insert(ObjectId::from(Tile(255, 2)), def(ObjectKind::OverrideObject(Tile(8, 12)), Some(ObjectCategory::Interactive)));
+        // Overrides a key
+        defs.insert(ObjectId::from(Tile(255, 4)), def(ObjectKind::OverrideObject(Tile(0, 22)), None));
+        defs
+    }
+
+    #[test]
+    fn categories_fall_back_to_the_original() {
+        let defs = test_defs();
+
+        assert_eq!(category_of(&defs, Tile(8, 12)), Some(ObjectCategory::Hazard));
+        assert_eq!(category_of(&defs, Tile(8, 13)), None);
+        assert_eq!(category_of(&defs, Tile(255, 1)), Some(ObjectCategory::Hazard));
+        assert_eq!(category_of(&defs, Tile(255, 2)), Some(ObjectCategory::Interactive));
+        assert_eq!(category_of(&defs, Tile(0, 22)), Some(ObjectCategory::Collectable));
+        assert_eq!(category_of(&defs, Tile(255, 4)), Some(ObjectCategory::Collectable));
+        assert_eq!(category_of(&defs, Tile(255, 3)), None);
+    }
+
+    #[test]
+    fn collectable_kinds_come_from_definitions() {
+        let defs = test_defs();
+
+        assert_eq!(CollectableKind::of(Tile(0, 22), &defs), Some(CollectableKind::Key));
+        assert_eq!(CollectableKind::of(Tile(8, 14), &defs), Some(CollectableKind::Other));
+        assert_eq!(CollectableKind::of(Tile(8, 12), &defs), None);
+        assert_eq!(CollectableKind::of(Tile(19, 60), &defs), None);
+    }
+}
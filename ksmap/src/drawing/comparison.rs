@@ -0,0 +1,22 @@
+use image::{imageops, Rgba, RgbaImage};
+
+use super::SCREEN_SIZE;
+
+/// Space between the two screens in pixels
+const GAP: u32 = 8;
+const BACKGROUND_COLOR: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// Puts the old version of a screen on the left and the new version on the right. A missing side,
+/// for screens that were added or removed, is left as a blank background.
+pub fn draw_screen_comparison(old: Option<&RgbaImage>, new: Option<&RgbaImage>) -> RgbaImage {
+    let mut canvas = RgbaImage::from_pixel(SCREEN_SIZE.0 * 2 + GAP, SCREEN_SIZE.1, BACKGROUND_COLOR);
+
+    if let Some(old) = old {
+        imageops::overlay(&mut canvas, old, 0, 0);
+    }
+    if let Some(new) = new {
+        imageops::overlay(&mut canvas, new, (SCREEN_SIZE.0 + GAP) as i64, 0);
+    }
+
+    canvas
+}
@@ -0,0 +1,72 @@
+use image::{Rgba, RgbaImage};
+
+use crate::analysis::TilesetUsage;
+use super::text;
+
+/// Brightness of tiles that are never used
+const UNUSED_BRIGHTNESS: f32 = 0.25;
+/// Opacity of the tint on used tiles
+const TINT_ALPHA: f32 = 0.5;
+const COLD_COLOR: [f32; 3] = [0.0, 64.0, 255.0];
+const HOT_COLOR: [f32; 3] = [255.0, 32.0, 0.0];
+
+/// Copies a tileset and tints each used tile from blue for the least used to red for the most used,
+/// on a log scale, and labels it with its count. Unused tiles are dimmed.
+pub fn draw_tileset_heatmap(tileset: &RgbaImage, usage: &TilesetUsage) -> RgbaImage {
+    let mut canvas = tileset.clone();
+    let max_count = usage.tiles.iter()
+        .map(|tile| tile.count)
+        .max()
+        .unwrap_or(0);
+
+    let mut counts = [0; 128];
+    for tile in &usage.tiles {
+        counts[tile.index as usize % 128] = tile.count;
+    }
+
+    // Tile 0 is the empty tile
+    for (index, count) in counts.into_iter().enumerate().skip(1) {
+        let (x, y) = super::tileset_index_to_pixels(index as u8);
+
+        if count == 0 {
+            for_each_pixel(&mut canvas, x, y, |pixel| {
+                for channel in &mut pixel.0[..3] {
+                    *channel = (*channel as f32 * UNUSED_BRIGHTNESS) as u8;
+                }
+            });
+            continue;
+        }
+
+        let heat = (count as f32).ln_1p() / (max_count as f32).ln_1p();
+        let color: [f32; 3] = std::array::from_fn(|i| COLD_COLOR[i] + (HOT_COLOR[i] - COLD_COLOR[i]) * heat);
+        for_each_pixel(&mut canvas, x, y, |pixel| {
+            let alpha = pixel.0[3] as f32 / 255.0;
+            for (channel, tint) in pixel.0[..3].iter_mut().zip(color) {
+                // Transparent pixels have no color of their own, so they take the tint's
+                *channel = (*channel as f32 * alpha * (1.0 - TINT_ALPHA) + tint * (1.0 - alpha * (1.0 - TINT_ALPHA))) as u8;
+            }
+            pixel.0[3] = u8::max(pixel.0[3], (TINT_ALPHA * 255.0) as u8);
+        });
+
+        text::draw_label(&mut canvas, x, y, &short_count(count), 1);
+    }
+
+    canvas
+}
+
+fn for_each_pixel(canvas: &mut RgbaImage, x: u32, y: u32, mut f: impl FnMut(&mut Rgba<u8>)) {
+    for py in y..u32::min(y + 24, canvas.height()) {
+        for px in x..u32::min(x + 24, canvas.width()) {
+            f(canvas.get_pixel_mut(px, py));
+        }
+    }
+}
+
+/// Formats a count in at most 5 characters so the label fits in a tile
+fn short_count(count: usize) -> String {
+    match count {
+        0..100_000 => count.to_string(),
+        100_000..100_000_000 => format!("{}K", count / 1000),
+        _ => format!("{}M", count / 1_000_000),
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use image::{Rgba, RgbaImage};
+
+use crate::{partition::Bounds, screen_map::ScreenMap, search::SearchMatch};
+use super::{SCREEN_SIZE, TILE_SIZE, screen_origin, text};
+
+const SCREEN_COLOR: Rgba<u8> = Rgba([48, 48, 48, 255]);
+const SCREEN_BORDER_COLOR: Rgba<u8> = Rgba([80, 80, 80, 255]);
+const HIGHLIGHT_COLOR: Rgba<u8> = Rgba([255, 64, 64, 255]);
+
+/// Draws a map of the world with each tile as a `tile_size`x`tile_size` square, where screens are
+/// gray and the tiles in `matches` are highlighted
+pub fn draw_search_highlights(screens: &ScreenMap, matches: &[SearchMatch], tile_size: u32) -> Result<RgbaImage> {
+    let bounds = Bounds::from_iter(screens.iter_positions());
+    let screen_width = SCREEN_SIZE.0 / TILE_SIZE * tile_size;
+    let screen_height = SCREEN_SIZE.1 / TILE_SIZE * tile_size;
+    // Screen origins are whole tiles, so they scale down from full size without rounding
+    let origin = |pos| {
+        let (x, y) = screen_origin(&bounds, pos, (0, 0));
+        (x / TILE_SIZE * tile_size, y / TILE_SIZE * tile_size)
+    };
+
+    let width = u32::try_from(bounds.width()).ok()
+        .and_then(|width| width.checked_mul(screen_width));
+    let height = u32::try_from(bounds.height()).ok()
+        .and_then(|height| height.checked_mul(screen_height));
+    let (Some(width), Some(height)) = (width, height) else {
+        return Err(anyhow!("World is too large to highlight: {bounds}"));
+    };
+    let mut canvas = RgbaImage::new(width, height);
+
+    for pos in screens.iter_positions() {
+        let (x, y) = origin(*pos);
+        text::fill_rect(&mut canvas, x, y, screen_width, screen_height, SCREEN_BORDER_COLOR);
+        text::fill_rect(&mut canvas, x + 1, y + 1, screen_width - 2, screen_height - 2, SCREEN_COLOR);
+    }
+
+    for found in matches {
+        let (tile_x, tile_y) = found.tile_position();
+        let (screen_x, screen_y) = origin(found.screen);
+        let x = screen_x + tile_x as u32 * tile_size;
+        let y = screen_y + tile_y as u32 * tile_size;
+        text::fill_rect(&mut canvas, x, y, tile_size, tile_size, HIGHLIGHT_COLOR);
+    }
+
+    Ok(canvas)
+}
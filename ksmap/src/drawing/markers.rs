@@ -0,0 +1,41 @@
+use image::{Rgba, RgbaImage};
+use rustc_hash::FxHashSet;
+
+use crate::{analysis::Collectable, partition::Partition};
+use super::{TILE_SIZE, screen_origin, text};
+
+const MARKER_COLOR: Rgba<u8> = Rgba([255, 220, 0, 255]);
+const MARKER_THICKNESS: u32 = 2;
+const LABEL_SCALE: u32 = 2;
+
+/// Outlines each collectable in a drawn partition and labels it with its position in
+/// `collectables`, starting from 1. `margin` must match the one the partition was drawn with.
+pub fn draw_collectable_markers(
+    canvas: &mut RgbaImage,
+    partition: &Partition,
+    margin: (u32, u32),
+    collectables: &[Collectable],
+) {
+    let bounds = partition.bounds();
+    let positions: FxHashSet<_> = partition.positions()
+        .iter()
+        .collect();
+
+    for (i, collectable) in collectables.iter().enumerate() {
+        if !positions.contains(&collectable.screen) {
+            continue;
+        }
+
+        let (tile_x, tile_y) = collectable.tile_position();
+        let (screen_x, screen_y) = screen_origin(&bounds, collectable.screen, margin);
+        let x = screen_x + tile_x as u32 * TILE_SIZE;
+        let y = screen_y + tile_y as u32 * TILE_SIZE;
+
+        text::fill_rect(canvas, x, y, TILE_SIZE, MARKER_THICKNESS, MARKER_COLOR);
+        text::fill_rect(canvas, x, y + TILE_SIZE - MARKER_THICKNESS, TILE_SIZE, MARKER_THICKNESS, MARKER_COLOR);
+        text::fill_rect(canvas, x, y, MARKER_THICKNESS, TILE_SIZE, MARKER_COLOR);
+        text::fill_rect(canvas, x + TILE_SIZE - MARKER_THICKNESS, y, MARKER_THICKNESS, TILE_SIZE, MARKER_COLOR);
+
+        text::draw_label(canvas, x, y + TILE_SIZE, &(i + 1).to_string(), LABEL_SCALE);
+    }
+}
@@ -3,7 +3,7 @@ use std::{fs, ops::RangeInclusive, path::Path};
 use anyhow::{anyhow, Result};
 use image::{codecs::png::PngEncoder, imageops, GenericImage, ImageEncoder, RgbaImage, SubImage};
 use rand::prelude::*;
-use libks::{ScreenCoord, map_bin::{LayerData, ScreenData, Tile}};
+use libks::{ScreenCoord, constants::{SCREEN_WIDTH, TILES_PER_LAYER}, map_bin::{LayerData, ScreenData, Tile}};
 use libks_ini::{Ini, VirtualSection};
 
 use crate::{
@@ -17,7 +17,44 @@ use crate::{
 };
 
 mod blend_modes;
+mod collision;
+mod comparison;
+mod heatmap;
+mod highlights;
+mod markers;
+mod overview;
+mod sync_overlay;
+mod text;
 pub use blend_modes::BlendMode;
+pub use comparison::draw_screen_comparison;
+pub use heatmap::draw_tileset_heatmap;
+pub use highlights::draw_search_highlights;
+pub use markers::draw_collectable_markers;
+pub use overview::{Overview, OverviewScale};
+pub use sync_overlay::draw_sync_overlay;
+
+/// The width and height of a tile in pixels
+pub const TILE_SIZE: u32 = 24;
+/// The width and height of a screen in pixels
+pub const SCREEN_SIZE: (u32, u32) = (
+    SCREEN_WIDTH as u32 * TILE_SIZE,
+    (TILES_PER_LAYER / SCREEN_WIDTH) as u32 * TILE_SIZE,
+);
+
+/// Opacity of lasers when both phases are drawn on top of each other
+const OVERLAID_LASER_ALPHA: f32 = 0.5;
+
+/// Brightness of the neighboring screens drawn in a partition's margin
+const MARGIN_BRIGHTNESS: f32 = 0.4;
+
+/// The top-left corner of the screen at `pos` in an image of `bounds` drawn with a margin of
+/// `margin` pixels
+fn screen_origin(bounds: &Bounds, pos: ScreenCoord, margin: (u32, u32)) -> (u32, u32) {
+    (
+        (pos.0 as i64 - bounds.x.start) as u32 * SCREEN_SIZE.0 + margin.0,
+        (pos.1 as i64 - bounds.y.start) as u32 * SCREEN_SIZE.1 + margin.1,
+    )
+}
 
 pub fn tileset_index_to_pixels(i: u8) -> (u32, u32) {
     (
@@ -56,11 +93,14 @@ struct ScreenContext<'a> {
     ini_section: Option<VirtualSection<'a>>,
     sync: ScreenSync,
     opts: DrawOptions,
+    alpha: f32,
 }
 
 #[derive(Clone, Copy, Default)]
 pub struct DrawOptions {
     pub editor_only: bool,
+    /// Draw a schematic of solid tiles and object categories instead of the screen's graphics
+    pub collision_map: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -71,34 +111,71 @@ struct Cursor {
 }
 
 pub fn draw_partition(ctx: DrawContext, partition: &Partition) -> Result<RgbaImage> {        
+    draw_partition_with_margin(ctx, partition, (0, 0))
+}
+
+/// Draws a partition surrounded by a margin of `margin` pixels on each side. The margin shows the
+/// neighboring screens that belong to other partitions, dimmed.
+pub fn draw_partition_with_margin(ctx: DrawContext, partition: &Partition, margin: (u32, u32)) -> Result<RgbaImage> {
     let bounds = partition.bounds();
-    let mut canvas = make_canvas(&bounds)?;
+    let mut canvas = make_canvas(&bounds, margin)?;
     for pos in partition {
         let Some(index_screen) = ctx.screens.index_of(pos) else { continue };
         let screen = &ctx.screens[index_screen];
         match draw_screen(ctx.seed, screen, index_screen, ctx.gfx, ctx.defs, ctx.ini, ctx.options, ctx.world_sync) {
             Ok(screen_image) => {
-                let canvas_x: u32 = ((screen.position.0 as i64 - bounds.x.start) * 600).try_into().unwrap();
-                let canvas_y: u32 = ((screen.position.1 as i64 - bounds.y.start) * 240).try_into().unwrap();
+                let (canvas_x, canvas_y) = screen_origin(&bounds, screen.position, margin);
                 canvas.copy_from(&screen_image, canvas_x, canvas_y)?;
             },
             Err(err) => return Err(err),
         }
     }
+    if margin != (0, 0) {
+        draw_margin(ctx, &bounds, margin, &mut canvas)?;
+    }
     Ok(canvas)
 }
 
-fn make_canvas(bounds: &Bounds) -> Result<RgbaImage> {
+fn draw_margin(ctx: DrawContext, bounds: &Bounds, margin: (u32, u32), canvas: &mut RgbaImage) -> Result<()> {
+    let margin_x = margin.0.div_ceil(SCREEN_SIZE.0) as i64;
+    let margin_y = margin.1.div_ceil(SCREEN_SIZE.1) as i64;
+
+    for y in (bounds.y.start - margin_y)..(bounds.y.end + margin_y) {
+        for x in (bounds.x.start - margin_x)..(bounds.x.end + margin_x) {
+            if bounds.x.contains(&x) && bounds.y.contains(&y) {
+                continue;
+            }
+            let Some(index_screen) = ctx.screens.index_of(&(x as i32, y as i32)) else { continue };
+            let screen = &ctx.screens[index_screen];
+            let mut screen_image = draw_screen(ctx.seed, screen, index_screen, ctx.gfx, ctx.defs, ctx.ini, ctx.options, ctx.world_sync)?;
+            for pixel in screen_image.pixels_mut() {
+                for channel in &mut pixel.0[..3] {
+                    *channel = (*channel as f32 * MARGIN_BRIGHTNESS) as u8;
+                }
+            }
+            // Screens in the margin can start above or to the left of the canvas
+            let canvas_x = (x - bounds.x.start) * SCREEN_SIZE.0 as i64 + margin.0 as i64;
+            let canvas_y = (y - bounds.y.start) * SCREEN_SIZE.1 as i64 + margin.1 as i64;
+            imageops::replace(canvas, &screen_image, canvas_x, canvas_y);
+        }
+    }
+
+    Ok(())
+}
+
+fn make_canvas(bounds: &Bounds, margin: (u32, u32)) -> Result<RgbaImage> {
     let (width, height) = bounds.size();
 
     let Ok(Some(width)) = u32::try_from(width)
-        .map(|width| width.checked_mul(600))
+        .map(|width| width.checked_mul(SCREEN_SIZE.0)
+            .and_then(|width| width.checked_add(margin.0 * 2)))
     else {
         return Err(anyhow!("Partition is too large: {bounds}"));
     };
 
     let Ok(Some(height)) = u32::try_from(height)
-        .map(|height| height.checked_mul(240))
+        .map(|height| height.checked_mul(SCREEN_SIZE.1)
+            .and_then(|height| height.checked_add(margin.1 * 2)))
     else {
         return Err(anyhow!("Partition {bounds} is too large"));
     };
@@ -165,6 +242,10 @@ pub fn draw_screen(
     options: DrawOptions,
     world_sync: &WorldSync,
 ) -> Result<RgbaImage> {
+    if options.collision_map {
+        return Ok(collision::draw_collision_screen(screen, gfx, defs, options));
+    }
+
     let ini_section = ini.section(&format!("x{}y{}", screen.position.0, screen.position.1));
     let is_overlay = ini_section
         .as_ref()
@@ -175,13 +256,12 @@ pub fn draw_screen(
         });
 
     // Create context
-    let group = world_sync.groups[index_screen];
-    let sync = ScreenSync::new(seed, screen, defs, group);
+    let sync = ScreenSync::new(seed, screen, defs, world_sync, index_screen);
     let mut ctx = ScreenContext {
         seed,
         screen_pos: screen.position,
         layer: 0,
-        image: RgbaImage::new(600, 240),
+        image: RgbaImage::new(SCREEN_SIZE.0, SCREEN_SIZE.1),
         tileset_a: gfx.tileset(screen.assets.tileset_a),
         tileset_b: gfx.tileset(screen.assets.tileset_b),
         gfx,
@@ -189,6 +269,7 @@ pub fn draw_screen(
         ini_section,
         sync,
         opts: options,
+        alpha: 1.0,
     };
     
     // Draw gradient
@@ -268,11 +349,15 @@ fn draw_object_layer(ctx: &mut ScreenContext, layer: &LayerData) {
         {
             continue;
         }
+        ctx.alpha = 1.0;
         if let Some(def) = object_def
             && let Some(phase) = &def.sync_params.laser_phase
-            && *phase != ctx.sync.group.laser_phase
         {
-            continue;
+            match ctx.sync.group.laser_phase {
+                Some(visible_phase) if visible_phase != *phase => continue,
+                Some(_) => {},
+                None => ctx.alpha = OVERLAID_LASER_ALPHA,
+            }
         }
 
         match curs.proxy_id.0 {
@@ -329,7 +414,9 @@ fn draw_object_with_offset(
         AnimSync::None => None,
         AnimSync::Screen => Some(ctx.sync.anim_t),
         AnimSync::Group => Some(ctx.sync.group.anim_t),
+        AnimSync::World => Some(ctx.sync.world_anim_t),
     };
+    let anim_t = anim_t.map(|anim_t| anim_t.wrapping_add(def.sync_params.sync_offset));
     draw_spritesheet(ctx, at_index as u8, &def.draw_params, anim_t, obj_image, offset, flip);
 }
 
@@ -378,7 +465,10 @@ fn draw_spritesheet(
             .write(at_index)
             .into_rng();
         let alpha = rng_alpha.random_range(alpha_range.clone()) as f32 / 255.0;
-        blend_modes::overlay_with_alpha(&mut ctx.image, &*frame, final_x, final_y, params.blend_mode, alpha);
+        blend_modes::overlay_with_alpha(&mut ctx.image, &*frame, final_x, final_y, params.blend_mode, alpha * ctx.alpha);
+    }
+    else if ctx.alpha < 1.0 {
+        blend_modes::overlay_with_alpha(&mut ctx.image, &*frame, final_x, final_y, params.blend_mode, ctx.alpha);
     }
     else {
         blend_modes::overlay(&mut ctx.image, &*frame, final_x, final_y, params.blend_mode);
@@ -435,18 +525,18 @@ fn draw_shift(ctx: &mut ScreenContext, curs: Cursor, vis_prop: &str, type_prop:
         .and_then(|section| section.get(type_prop))
         .unwrap_or("0")
     {
-        "0" => ObjectVariant::Spot,
-        "1" => ObjectVariant::Floor,
-        "2" => ObjectVariant::Circle,
-        "3" => ObjectVariant::Square,
-        _ => ObjectVariant::Spot,
+        "0" => ObjectVariant::SPOT,
+        "1" => ObjectVariant::FLOOR,
+        "2" => ObjectVariant::CIRCLE,
+        "3" => ObjectVariant::SQUARE,
+        _ => ObjectVariant::SPOT,
     };
 
     draw_object(ctx, curs.i, curs.proxy_id.into_variant(shift_type));
 }
 
 fn draw_with_glow(ctx: &mut ScreenContext, curs: Cursor) {
-    draw_object(ctx, curs.i, curs.proxy_id.to_variant(ObjectVariant::Glow));
+    draw_object(ctx, curs.i, curs.proxy_id.to_variant(ObjectVariant::GLOW));
     draw_object(ctx, curs.i, curs.actual_id);
 }
 
@@ -0,0 +1,182 @@
+use anyhow::{anyhow, Result};
+use image::{Rgba, RgbaImage};
+use libks::ScreenCoord;
+
+use crate::partition::{Bounds, Partition};
+use super::{SCREEN_SIZE, text};
+
+const OUTLINE_COLORS: [Rgba<u8>; 6] = [
+    Rgba([255, 64, 64, 255]),
+    Rgba([64, 255, 64, 255]),
+    Rgba([64, 160, 255, 255]),
+    Rgba([255, 255, 64, 255]),
+    Rgba([255, 64, 255, 255]),
+    Rgba([64, 255, 255, 255]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverviewScale {
+    /// One pixel per tile, averaged from the tile's 24x24 pixels
+    Tile,
+    /// One pixel per screen, averaged from the whole screen
+    Screen,
+}
+
+impl OverviewScale {
+    /// The size of one screen on the overview in pixels
+    pub fn screen_size(self) -> (u32, u32) {
+        match self {
+            OverviewScale::Tile => (25, 10),
+            OverviewScale::Screen => (1, 1),
+        }
+    }
+}
+
+/// A downscaled image of the whole world, built up from the images of each partition, with each
+/// partition outlined and numbered
+pub struct Overview {
+    canvas: RgbaImage,
+    bounds: Bounds,
+    scale: OverviewScale,
+}
+
+impl Overview {
+    pub fn new(partitions: &[Partition], scale: OverviewScale) -> Result<Self> {
+        let bounds = partitions.iter()
+            .map(Partition::bounds)
+            .reduce(|a, b| Bounds::union(&a, &b))
+            .unwrap_or(Bounds { x: 0..0, y: 0..0 });
+        Self::with_bounds(bounds, scale)
+    }
+
+    pub fn with_bounds(bounds: Bounds, scale: OverviewScale) -> Result<Self> {
+        let (screen_width, screen_height) = scale.screen_size();
+
+        let width = u32::try_from(bounds.width()).ok()
+            .and_then(|width| width.checked_mul(screen_width));
+        let height = u32::try_from(bounds.height()).ok()
+            .and_then(|height| height.checked_mul(screen_height));
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(anyhow!("World is too large for an overview: {bounds}"));
+        };
+
+        Ok(Self {
+            canvas: RgbaImage::new(width, height),
+            bounds,
+            scale,
+        })
+    }
+
+    /// Downscales the screens of a partition from its drawn image. `margin` must match the one the
+    /// partition was drawn with.
+    pub fn add_partition(&mut self, image: &RgbaImage, partition: &Partition, margin: (u32, u32)) {
+        let partition_bounds = partition.bounds();
+
+        for pos in partition {
+            let src = super::screen_origin(&partition_bounds, *pos, margin);
+            self.add_block(image, src, *pos);
+        }
+    }
+
+    /// Downscales the drawn image of a single screen
+    pub fn add_screen(&mut self, image: &RgbaImage, pos: ScreenCoord) {
+        self.add_block(image, (0, 0), pos);
+    }
+
+    fn add_block(&mut self, image: &RgbaImage, src: (u32, u32), pos: ScreenCoord) {
+        let (screen_width, screen_height) = self.scale.screen_size();
+        let block_width = SCREEN_SIZE.0 / screen_width;
+        let block_height = SCREEN_SIZE.1 / screen_height;
+        let (dst_x, dst_y) = self.screen_origin(pos);
+
+        for cell_y in 0..screen_height {
+            for cell_x in 0..screen_width {
+                let color = average_color(
+                    image,
+                    src.0 + cell_x * block_width,
+                    src.1 + cell_y * block_height,
+                    block_width,
+                    block_height,
+                );
+                self.canvas.put_pixel(dst_x + cell_x, dst_y + cell_y, color);
+            }
+        }
+    }
+
+    /// Darkens a screen that was already added
+    pub fn dim_screen(&mut self, pos: ScreenCoord, brightness: f32) {
+        let (screen_width, screen_height) = self.scale.screen_size();
+        let (x, y) = self.screen_origin(pos);
+
+        for py in y..y + screen_height {
+            for px in x..x + screen_width {
+                for channel in &mut self.canvas.get_pixel_mut(px, py).0[..3] {
+                    *channel = (*channel as f32 * brightness) as u8;
+                }
+            }
+        }
+    }
+
+    /// Draws a 1px outline just inside the edges of a screen
+    pub fn outline_screen(&mut self, pos: ScreenCoord, color: Rgba<u8>) {
+        let (screen_width, screen_height) = self.scale.screen_size();
+        let (x, y) = self.screen_origin(pos);
+
+        text::fill_rect(&mut self.canvas, x, y, screen_width, 1, color);
+        text::fill_rect(&mut self.canvas, x, y + screen_height - 1, screen_width, 1, color);
+        text::fill_rect(&mut self.canvas, x, y, 1, screen_height, color);
+        text::fill_rect(&mut self.canvas, x + screen_width - 1, y, 1, screen_height, color);
+    }
+
+    fn screen_origin(&self, pos: ScreenCoord) -> (u32, u32) {
+        let (screen_width, screen_height) = self.scale.screen_size();
+        (
+            (pos.0 as i64 - self.bounds.x.start) as u32 * screen_width,
+            (pos.1 as i64 - self.bounds.y.start) as u32 * screen_height,
+        )
+    }
+
+    /// Outlines each partition and labels it with its position in the list, starting from 1
+    pub fn draw_outlines(&mut self, partitions: &[Partition]) {
+        let (screen_width, screen_height) = self.scale.screen_size();
+
+        for (i, partition) in partitions.iter().enumerate() {
+            let bounds = partition.bounds();
+            let x = (bounds.x.start - self.bounds.x.start) as u32 * screen_width;
+            let y = (bounds.y.start - self.bounds.y.start) as u32 * screen_height;
+            let width = bounds.width() as u32 * screen_width;
+            let height = bounds.height() as u32 * screen_height;
+            let color = OUTLINE_COLORS[i % OUTLINE_COLORS.len()];
+
+            text::fill_rect(&mut self.canvas, x, y, width, 1, color);
+            text::fill_rect(&mut self.canvas, x, y + height - 1, width, 1, color);
+            text::fill_rect(&mut self.canvas, x, y, 1, height, color);
+            text::fill_rect(&mut self.canvas, x + width - 1, y, 1, height, color);
+
+            text::draw_label(&mut self.canvas, x + 1, y + 1, &(i + 1).to_string(), 1);
+        }
+    }
+
+    pub fn into_image(self) -> RgbaImage {
+        self.canvas
+    }
+}
+
+fn average_color(image: &RgbaImage, x: u32, y: u32, width: u32, height: u32) -> Rgba<u8> {
+    let mut sum = [0u64; 4];
+    let mut count = 0;
+
+    for py in y..u32::min(y + height, image.height()) {
+        for px in x..u32::min(x + width, image.width()) {
+            for (total, channel) in sum.iter_mut().zip(image.get_pixel(px, py).0) {
+                *total += channel as u64;
+            }
+            count += 1;
+        }
+    }
+
+    if count == 0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+    Rgba(sum.map(|total| (total / count) as u8))
+}
@@ -0,0 +1,71 @@
+use std::hash::{Hash, Hasher};
+
+use image::RgbaImage;
+use rustc_hash::FxHasher;
+
+use crate::{
+    definitions::LaserPhase,
+    partition::Partition,
+    screen_map::ScreenMap,
+    synchronization::WorldSync,
+};
+use super::{SCREEN_SIZE, screen_origin, text};
+
+const TINT_ALPHA: f32 = 0.35;
+const LABEL_SCALE: u32 = 3;
+const LABEL_PADDING: u32 = 4;
+
+/// Tints each screen of a drawn partition by its sync group and labels it with the group's ID,
+/// animation time and laser phase, e.g. `#12 T3051 LR`. `margin` must match the one the partition
+/// was drawn with.
+pub fn draw_sync_overlay(
+    canvas: &mut RgbaImage,
+    partition: &Partition,
+    margin: (u32, u32),
+    screens: &ScreenMap,
+    world_sync: &WorldSync,
+) {
+    let bounds = partition.bounds();
+    for pos in partition {
+        let Some(index_screen) = screens.index_of(pos) else { continue };
+        let group_id = world_sync.group_ids[index_screen];
+        let group = world_sync.groups[index_screen];
+
+        let (canvas_x, canvas_y) = screen_origin(&bounds, *pos, margin);
+        tint_screen(canvas, canvas_x, canvas_y, group_color(group_id));
+
+        let phase = match group.laser_phase {
+            Some(LaserPhase::Red) => "R",
+            Some(LaserPhase::Green) => "G",
+            None => "RG",
+        };
+        let label = format!("#{group_id} T{} L{phase}", group.anim_t);
+        text::draw_label(canvas, canvas_x + LABEL_PADDING, canvas_y + LABEL_PADDING, &label, LABEL_SCALE);
+    }
+}
+
+fn group_color(group_id: usize) -> [u8; 3] {
+    let mut hasher = FxHasher::default();
+    group_id.hash(&mut hasher);
+    let hash = hasher.finish();
+
+    // Keep every channel reasonably bright so the tint is visible on dark screens
+    [
+        (hash as u8) | 0x40,
+        ((hash >> 8) as u8) | 0x40,
+        ((hash >> 16) as u8) | 0x40,
+    ]
+}
+
+fn tint_screen(canvas: &mut RgbaImage, x: u32, y: u32, color: [u8; 3]) {
+    for py in y..u32::min(y + SCREEN_SIZE.1, canvas.height()) {
+        for px in x..u32::min(x + SCREEN_SIZE.0, canvas.width()) {
+            let pixel = canvas.get_pixel_mut(px, py);
+            for (channel, tint) in pixel.0[..3].iter_mut().zip(color) {
+                let blended = *channel as f32 * (1.0 - TINT_ALPHA) + tint as f32 * TINT_ALPHA;
+                *channel = blended.round() as u8;
+            }
+            pixel.0[3] = 255;
+        }
+    }
+}
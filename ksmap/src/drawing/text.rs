@@ -0,0 +1,67 @@
+use image::{Rgba, RgbaImage};
+
+const GLYPH_WIDTH: u32 = 3;
+const GLYPH_HEIGHT: u32 = 5;
+
+/// Draws white text on a black box with its top left corner at (x, y), with each glyph pixel
+/// drawn as a `scale`x`scale` square
+pub(super) fn draw_label(canvas: &mut RgbaImage, x: u32, y: u32, text: &str, scale: u32) {
+    let advance = (GLYPH_WIDTH + 1) * scale;
+    let width = text.len() as u32 * advance + scale;
+    let height = (GLYPH_HEIGHT + 2) * scale;
+
+    fill_rect(canvas, x, y, width, height, Rgba([0, 0, 0, 255]));
+
+    let mut glyph_x = x + scale;
+    let glyph_y = y + scale;
+    for c in text.chars() {
+        let rows = glyph(c);
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> col) != 0 {
+                    fill_rect(
+                        canvas,
+                        glyph_x + col * scale,
+                        glyph_y + row as u32 * scale,
+                        scale,
+                        scale,
+                        Rgba([255, 255, 255, 255]),
+                    );
+                }
+            }
+        }
+        glyph_x += advance;
+    }
+}
+
+pub(super) fn fill_rect(canvas: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, color: Rgba<u8>) {
+    for py in y..u32::min(y + height, canvas.height()) {
+        for px in x..u32::min(x + width, canvas.width()) {
+            canvas.put_pixel(px, py, color);
+        }
+    }
+}
+
+/// 3x5 bitmap glyphs, one row per byte with the leftmost pixel in the high bit
+fn glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        'G' => [0b111, 0b100, 0b101, 0b101, 0b111],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        _ => [0; 5],
+    }
+}
@@ -13,7 +13,7 @@ use rustc_hash::FxHashMap;
 
 use crate::{
     definitions::{ObjectDef, ObjectDefs, ObjectKind, OcoSupport},
-    id::{ObjectId, ObjectVariant},
+    id::ObjectId,
 };
 
 mod png_decoder;
@@ -211,10 +211,8 @@ impl<'a> Graphics<'a> {
         let ObjectId(tile, variant) = id;
         let suffix = match def.and_then(|def| def.path.as_ref()) {
             Some(path) => path,
-            None => match variant {
-                ObjectVariant::None => &format!("Bank{}/Object{}.png", tile.0, tile.1),
-                _ => &format!("Bank{}/Object{}_{}.png", tile.0, tile.1, variant),
-            },
+            None if variant.is_none() => &format!("Bank{}/Object{}.png", tile.0, tile.1),
+            None => &format!("Bank{}/Object{}_{}.png", tile.0, tile.1, variant),
         };
         
         let image = self.load_image(self.paths.templates.join(suffix), MagicColor::FORCE_MAGENTA)?;
@@ -1,7 +1,11 @@
-use std::fmt::{self, Display};
+use std::{
+    fmt::{self, Display},
+    sync::{LazyLock, Mutex, MutexGuard},
+};
 
 use libks::map_bin::Tile;
-use serde::Deserialize;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Deserializer};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 #[serde(try_from = "String")]
@@ -18,21 +22,75 @@ impl ObjectId {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Deserialize)]
-#[serde(try_from = "String")]
-pub enum ObjectVariant {
-    #[default]
-    None,
-    Left,
-    Glow,
-    Spot,
-    Floor,
-    Circle,
-    Square,
-    A,
-    B,
-    C,
-    D,
+/// The name of an alternate sprite for an object, e.g. `Glow` in `1-5 Glow`.
+/// Names are interned, so variants are cheap to copy and are compared by their interned index.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ObjectVariant(u32);
+
+/// The names of the variants with constants, in the order of their indices
+const BUILTIN_NAMES: [&str; 11] = ["", "Left", "Glow", "Spot", "Floor", "Circle", "Square", "A", "B", "C", "D"];
+
+struct VariantNames {
+    names: Vec<&'static str>,
+    indices: FxHashMap<&'static str, u32>,
+}
+
+static VARIANT_NAMES: LazyLock<Mutex<VariantNames>> = LazyLock::new(|| {
+    let names = BUILTIN_NAMES.to_vec();
+    let indices = names.iter()
+        .enumerate()
+        .map(|(i, name)| (*name, i as u32))
+        .collect();
+    Mutex::new(VariantNames { names, indices })
+});
+
+fn variant_names() -> MutexGuard<'static, VariantNames> {
+    VARIANT_NAMES.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl ObjectVariant {
+    pub const NONE: Self = Self(0);
+    pub const LEFT: Self = Self(1);
+    pub const GLOW: Self = Self(2);
+    pub const SPOT: Self = Self(3);
+    pub const FLOOR: Self = Self(4);
+    pub const CIRCLE: Self = Self(5);
+    pub const SQUARE: Self = Self(6);
+    pub const A: Self = Self(7);
+    pub const B: Self = Self(8);
+    pub const C: Self = Self(9);
+    pub const D: Self = Self(10);
+
+    /// Returns the variant with the given name, interning the name if it hasn't been seen before.
+    pub fn new(name: &str) -> Self {
+        let mut names = variant_names();
+        if let Some(index) = names.indices.get(name) {
+            return Self(*index);
+        }
+
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        let index = names.names.len() as u32;
+        names.names.push(name);
+        names.indices.insert(name, index);
+        Self(index)
+    }
+
+    pub fn name(&self) -> &'static str {
+        variant_names().names[self.0 as usize]
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+impl fmt::Debug for ObjectVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ObjectVariant")
+            .field(&self.name())
+            .finish()
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -47,34 +105,36 @@ pub enum ObjectIdParseError {
 
 #[derive(Debug, thiserror::Error)]
 pub enum ObjectVariantParseError {
-    #[error("Unknown object variant: {0}")]
+    #[error("Unknown object variant: {0:?} (names may only contain letters, digits and underscores)")]
     UnknownVariant(String),
 }
 
 impl Display for ObjectId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self.1 {
-            ObjectVariant::None => write!(f, "{}-{}", self.0.0, self.0.1),
-            _ => write!(f, "{}-{} {}", self.0.0, self.0.1, self.1),
+        if self.1.is_none() {
+            write!(f, "{}-{}", self.0.0, self.0.1)
+        }
+        else {
+            write!(f, "{}-{} {}", self.0.0, self.0.1, self.1)
         }
     }
 }
 
 impl From<(u8, u8)> for ObjectId {
     fn from(value: (u8, u8)) -> Self {
-        Self(Tile(value.0, value.1), ObjectVariant::None)
+        Self(Tile(value.0, value.1), ObjectVariant::NONE)
     }
 }
 
 impl From<Tile> for ObjectId {
     fn from(value: Tile) -> Self {
-        Self(value, ObjectVariant::None)
+        Self(value, ObjectVariant::NONE)
     }
 }
 
 impl From<&Tile> for ObjectId {
     fn from(value: &Tile) -> Self {
-        Self(*value, ObjectVariant::None)
+        Self(*value, ObjectVariant::NONE)
     }
 }
 
@@ -84,7 +144,7 @@ impl TryFrom<&str> for ObjectId {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         let (bank_and_index, variant) = match value.split_once(' ') {
             Some((id, variant)) => (id, ObjectVariant::try_from(variant)?),
-            None => (value, ObjectVariant::None),
+            None => (value, ObjectVariant::NONE),
         };
 
         let Some((bank, index)) = bank_and_index.split_once('-') else {
@@ -115,20 +175,7 @@ impl TryFrom<String> for ObjectId {
 
 impl Display for ObjectVariant {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let s = match self {
-            ObjectVariant::None => "",
-            ObjectVariant::Left => "Left",
-            ObjectVariant::Glow => "Glow",
-            ObjectVariant::Spot => "Spot",
-            ObjectVariant::Floor => "Floor",
-            ObjectVariant::Circle => "Circle",
-            ObjectVariant::Square => "Square",
-            ObjectVariant::A => "A",
-            ObjectVariant::B => "B",
-            ObjectVariant::C => "C",
-            ObjectVariant::D => "D",
-        };
-        f.write_str(s)
+        f.write_str(self.name())
     }
 }
 
@@ -136,21 +183,22 @@ impl TryFrom<&str> for ObjectVariant {
     type Error = ObjectVariantParseError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let variant = match value {
-            "" => ObjectVariant::None,
-            "Left" => ObjectVariant::Left,
-            "Glow" => ObjectVariant::Glow,
-            "Spot" => ObjectVariant::Spot,
-            "Floor" => ObjectVariant::Floor,
-            "Circle" => ObjectVariant::Circle,
-            "Square" => ObjectVariant::Square,
-            "A" => ObjectVariant::A,
-            "B" => ObjectVariant::B,
-            "C" => ObjectVariant::C,
-            "D" => ObjectVariant::D,
-            _ => return Err(ObjectVariantParseError::UnknownVariant(value.to_owned())),
-        };
-        Ok(variant)
+        // Variant names become part of template file names, so keep them simple
+        if !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(ObjectVariantParseError::UnknownVariant(value.to_owned()));
+        }
+        Ok(ObjectVariant::new(value))
+    }
+}
+
+impl<'de> Deserialize<'de> for ObjectVariant {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        ObjectVariant::try_from(name.as_str())
+            .map_err(serde::de::Error::custom)
     }
 }
 
@@ -161,3 +209,42 @@ impl TryFrom<String> for ObjectVariant {
         ObjectVariant::try_from(value.as_str())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_names_match_their_constants() {
+        assert_eq!(ObjectVariant::new(""), ObjectVariant::NONE);
+        assert_eq!(ObjectVariant::new("Left"), ObjectVariant::LEFT);
+        assert_eq!(ObjectVariant::new("Glow"), ObjectVariant::GLOW);
+        assert_eq!(ObjectVariant::new("Square"), ObjectVariant::SQUARE);
+        assert_eq!(ObjectVariant::new("D"), ObjectVariant::D);
+        assert_eq!(ObjectVariant::SPOT.name(), "Spot");
+        assert!(ObjectVariant::NONE.is_none());
+        assert!(!ObjectVariant::A.is_none());
+    }
+
+    #[test]
+    fn new_names_are_interned() {
+        let sparkle = ObjectVariant::try_from("Sparkle").unwrap();
+        assert_eq!(sparkle, ObjectVariant::new("Sparkle"));
+        assert_eq!(sparkle.name(), "Sparkle");
+        assert_ne!(sparkle, ObjectVariant::new("sparkle"));
+        assert_ne!(sparkle, ObjectVariant::GLOW);
+    }
+
+    #[test]
+    fn object_ids_parse_with_and_without_variants() {
+        assert_eq!(ObjectId::try_from("1-5").unwrap(), ObjectId(Tile(1, 5), ObjectVariant::NONE));
+        assert_eq!(ObjectId::try_from("1-5 Glow").unwrap(), ObjectId(Tile(1, 5), ObjectVariant::GLOW));
+        assert_eq!(ObjectId::try_from("0-14 Tall_2").unwrap().to_string(), "0-14 Tall_2");
+        assert!(matches!(
+            ObjectId::try_from("1-5 Not-Allowed"),
+            Err(ObjectIdParseError::ObjectVariantParse(ObjectVariantParseError::UnknownVariant(_))),
+        ));
+        assert!(matches!(ObjectId::try_from("15"), Err(ObjectIdParseError::MissingSeparator(_))));
+        assert!(matches!(ObjectId::try_from("1-x"), Err(ObjectIdParseError::InvalidIndex(_))));
+    }
+}
@@ -5,5 +5,10 @@ pub mod partition;
 pub mod synchronization;
 pub mod screen_map;
 pub mod analysis;
+pub mod check;
+pub mod links;
+pub mod diff;
+pub mod screen_graph;
+pub mod search;
 pub mod id;
 pub mod seed;
@@ -0,0 +1,96 @@
+use std::fmt::{self, Display};
+
+use libks::{ScreenCoord, map_bin::{LayerData, Tile}};
+use libks_ini::{Ini, VirtualSection};
+
+use crate::screen_map::ScreenMap;
+
+/// A one-way connection from one screen to another that isn't next to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScreenLink {
+    pub from: ScreenCoord,
+    pub to: ScreenCoord,
+    pub kind: LinkKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkKind {
+    /// A shift, labelled `A`, `B` or `C`
+    Shift(char),
+    /// A warp across the edge of the screen, labelled `U`, `D`, `L` or `R`
+    Warp(char),
+}
+
+impl Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkKind::Shift(label) => write!(f, "Shift {label}"),
+            LinkKind::Warp(label) => write!(f, "Warp {label}"),
+        }
+    }
+}
+
+/// Finds the warps and shifts defined in World.ini for every screen in the map.
+///
+/// Warps lead from the screen the player would normally walk into to the screen offset from it by
+/// `WarpX(U)`, `WarpY(U)` etc. Shifts only count on screens that have the shift object (0-14, 0-15
+/// or 0-16 for A, B or C) and lead to the screen offset from the current one by `ShiftXMap(A)` and
+/// `ShiftYMap(A)`, or to that screen itself if `ShiftAbsoluteTarget(A)` is set. `ShiftX(A)` and
+/// `ShiftY(A)` are where the player lands on the target screen, so they don't matter here.
+pub fn find_screen_links(screens: &ScreenMap, ini: &Ini) -> Vec<ScreenLink> {
+    let mut links = Vec::new();
+
+    for screen in screens.iter() {
+        let pos = &screen.position;
+        let Some(section) = ini.section(&format!("x{}y{}", pos.0, pos.1)) else { continue };
+
+        for (dir, step) in [('U', (0, -1)), ('D', (0, 1)), ('L', (-1, 0)), ('R', (1, 0))] {
+            let offset_x = parse_key(&section, &format!("WarpX({dir})"));
+            let offset_y = parse_key(&section, &format!("WarpY({dir})"));
+            if offset_x.is_none() && offset_y.is_none() {
+                continue;
+            }
+
+            let to = (
+                pos.0 + step.0 + offset_x.unwrap_or(0),
+                pos.1 + step.1 + offset_y.unwrap_or(0),
+            );
+            links.push(ScreenLink { from: *pos, to, kind: LinkKind::Warp(dir) });
+        }
+
+        for (shift, object) in [('A', 14), ('B', 15), ('C', 16)] {
+            let has_shift = screen.layers[4..].iter()
+                .any(|LayerData(layer)| layer.contains(&Tile(0, object)));
+            if !has_shift {
+                continue;
+            }
+
+            let is_absolute = section.get(&format!("ShiftAbsoluteTarget({shift})"))
+                .is_some_and(|v| v.trim().eq_ignore_ascii_case("true"));
+            let map_x = parse_key(&section, &format!("ShiftXMap({shift})"));
+            let map_y = parse_key(&section, &format!("ShiftYMap({shift})"));
+
+            let to = if is_absolute {
+                let (Some(map_x), Some(map_y)) = (map_x, map_y) else { continue };
+                (map_x, map_y)
+            }
+            else {
+                if map_x.is_none() && map_y.is_none() {
+                    continue;
+                }
+                (pos.0 + map_x.unwrap_or(0), pos.1 + map_y.unwrap_or(0))
+            };
+
+            if to != *pos {
+                links.push(ScreenLink { from: *pos, to, kind: LinkKind::Shift(shift) });
+            }
+        }
+    }
+
+    links
+}
+
+fn parse_key(section: &VirtualSection, key: &str) -> Option<i32> {
+    section.get(key)
+        .and_then(|v| str::parse(v.trim()).ok())
+}
@@ -1,8 +1,9 @@
 use std::ops::Range;
 
 use libks::ScreenCoord;
+use serde::Serialize;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 pub struct Bounds {
     pub x: Range<i64>,
     pub y: Range<i64>,
@@ -0,0 +1,240 @@
+use libks::ScreenCoord;
+
+use crate::{
+    partition::{islands, merge_redundant_partitions},
+    screen_map::ScreenMap,
+};
+use super::{Bounds, Partition, Partitioner, SizeLimit};
+
+pub struct CompactPartitioner {
+    pub max_size: SizeLimit,
+    pub gap: u64,
+    pub min_fill: f64,
+    pub force: bool,
+}
+
+impl Default for CompactPartitioner {
+    fn default() -> Self {
+        Self {
+            max_size: SizeLimit::default(),
+            gap: 10,
+            min_fill: 0.5,
+            force: false,
+        }
+    }
+}
+
+impl Partitioner for CompactPartitioner {
+    fn partitions(&self, screens: &ScreenMap) -> Vec<Partition> {
+        let positions = screens.iter_positions()
+            .copied()
+            .collect();
+        let partition = Partition::new(positions);
+        
+        if !self.force
+            && !islands::is_partition_too_large(&partition, self.max_size)
+        {
+            return vec![partition];
+        }
+        
+        let graph = islands::partition_into_graph(partition, self.gap);
+        let mut partitions = Vec::new();
+        for island in islands::graph_into_partitions(graph) {
+            cut_recursively(island.positions, self.max_size, self.min_fill, &mut partitions);
+        }
+        merge_redundant_partitions(&mut partitions);
+        partitions
+    }
+}
+
+/// Splits a set of screens with guillotine cuts until every piece fits in `max_size` and is at
+/// least `min_fill` full, or can't be made any emptier by cutting it.
+fn cut_recursively(
+    positions: Vec<ScreenCoord>,
+    max_size: SizeLimit,
+    min_fill: f64,
+    partitions: &mut Vec<Partition>,
+) {
+    let bounds = Bounds::from(positions.as_slice());
+    let is_too_large = !max_size.fits(&bounds);
+    let empty_area = empty_area(&bounds, positions.len());
+    let fill = positions.len() as f64 / area(&bounds) as f64;
+    
+    if positions.len() <= 1 || (!is_too_large && fill >= min_fill) {
+        partitions.push(Partition::new(positions));
+        return;
+    }
+    
+    let Some(cut) = best_cut(&positions) else {
+        partitions.push(Partition::new(positions));
+        return;
+    };
+    
+    // Only cut a partition that fits if it actually saves space
+    if !is_too_large && cut.empty_area >= empty_area {
+        partitions.push(Partition::new(positions));
+        return;
+    }
+    
+    let (first, second): (Vec<_>, Vec<_>) = positions.into_iter()
+        .partition(|pos| match cut.axis {
+            Axis::X => (pos.0 as i64) < cut.at,
+            Axis::Y => (pos.1 as i64) < cut.at,
+        });
+    cut_recursively(first, max_size, min_fill, partitions);
+    cut_recursively(second, max_size, min_fill, partitions);
+}
+
+const EMPTY_BOUNDS: Bounds = Bounds { x: 0..0, y: 0..0 };
+
+#[derive(Debug, Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cut {
+    axis: Axis,
+    /// Screens with a coordinate less than this go on the first side of the cut
+    at: i64,
+    /// The total empty area of the bounding boxes on both sides of the cut
+    empty_area: u64,
+    /// The difference in the number of screens on either side of the cut
+    imbalance: usize,
+}
+
+/// Finds the axis-aligned cut that leaves the least total empty area on either side
+fn best_cut(positions: &[ScreenCoord]) -> Option<Cut> {
+    let mut best: Option<Cut> = None;
+    
+    for axis in [Axis::X, Axis::Y] {
+        let mut sorted = positions.to_vec();
+        let key = |pos: &ScreenCoord| match axis {
+            Axis::X => pos.0,
+            Axis::Y => pos.1,
+        };
+        sorted.sort_by_key(key);
+        
+        // prefix[i] bounds sorted[..i] and suffix[i] bounds sorted[i..]
+        let mut prefix = Vec::with_capacity(sorted.len() + 1);
+        prefix.push(EMPTY_BOUNDS);
+        for i in 1..=sorted.len() {
+            prefix.push(extend(&prefix[i - 1], &sorted[i - 1]));
+        }
+        let mut suffix = vec![EMPTY_BOUNDS; sorted.len() + 1];
+        for i in (0..sorted.len()).rev() {
+            suffix[i] = extend(&suffix[i + 1], &sorted[i]);
+        }
+        
+        for i in 1..sorted.len() {
+            // Screens in the same row or column must stay together
+            if key(&sorted[i - 1]) == key(&sorted[i]) {
+                continue;
+            }
+            
+            let empty_area = self::empty_area(&prefix[i], i)
+                + self::empty_area(&suffix[i], sorted.len() - i);
+            // Break ties with the most balanced cut to avoid slicing off one screen at a time
+            let imbalance = i.abs_diff(sorted.len() - i);
+            if best.is_none_or(|best| (empty_area, imbalance) < (best.empty_area, best.imbalance)) {
+                best = Some(Cut {
+                    axis,
+                    at: key(&sorted[i]) as i64,
+                    empty_area,
+                    imbalance,
+                });
+            }
+        }
+    }
+    
+    best
+}
+
+fn extend(bounds: &Bounds, pos: &ScreenCoord) -> Bounds {
+    let single = Bounds::from_iter([pos]);
+    if bounds.is_empty() {
+        single
+    }
+    else {
+        Bounds::union(bounds, &single)
+    }
+}
+
+fn area(bounds: &Bounds) -> u64 {
+    bounds.width() * bounds.height()
+}
+
+fn empty_area(bounds: &Bounds, n_screens: usize) -> u64 {
+    area(bounds).saturating_sub(n_screens as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every screen in a rectangle, sorted
+    fn rect(x: std::ops::Range<i32>, y: std::ops::Range<i32>) -> Vec<ScreenCoord> {
+        x.flat_map(|x| y.clone().map(move |y| (x, y))).collect()
+    }
+
+    fn cut(positions: Vec<ScreenCoord>, max_size: SizeLimit, min_fill: f64) -> Vec<Vec<ScreenCoord>> {
+        let mut partitions = Vec::new();
+        cut_recursively(positions, max_size, min_fill, &mut partitions);
+        let mut pieces: Vec<_> = partitions.into_iter()
+            .map(|partition| {
+                let mut positions = partition.positions().to_vec();
+                positions.sort();
+                positions
+            })
+            .collect();
+        pieces.sort();
+        pieces
+    }
+
+    #[test]
+    fn best_cut_keeps_rows_and_columns_together() {
+        let cut = best_cut(&[(0, 0), (0, 5)]).unwrap();
+        assert!(matches!(cut.axis, Axis::Y));
+        assert_eq!(cut.at, 5);
+        assert_eq!(cut.empty_area, 0);
+    }
+
+    #[test]
+    fn best_cut_prefers_balanced_cuts() {
+        let cut = best_cut(&rect(0..8, 0..1)).unwrap();
+        assert!(matches!(cut.axis, Axis::X));
+        assert_eq!(cut.at, 4);
+        assert_eq!(cut.imbalance, 0);
+    }
+
+    #[test]
+    fn full_partition_that_fits_is_not_cut() {
+        let positions = rect(0..5, 0..3);
+        assert_eq!(cut(positions.clone(), SizeLimit::default(), 0.5), vec![positions]);
+    }
+
+    #[test]
+    fn sparse_partition_is_cut_along_its_empty_area() {
+        // An L made of a row and a column, which is 19% full as one piece
+        let mut positions = rect(0..10, 0..1);
+        positions.extend(rect(0..1, 1..10));
+
+        // Cutting off the column or the row is equally good, and columns are tried first
+        assert_eq!(cut(positions, SizeLimit::default(), 0.5), vec![
+            rect(0..1, 0..10),
+            rect(1..10, 0..1),
+        ]);
+    }
+
+    #[test]
+    fn large_partition_is_cut_to_fit() {
+        let max_size = SizeLimit::new(4, 4);
+        let pieces = cut(rect(0..10, 0..10), max_size, 0.5);
+
+        assert!(pieces.iter().all(|piece| max_size.fits(&Bounds::from(piece.as_slice()))));
+        let mut all: Vec<_> = pieces.into_iter().flatten().collect();
+        all.sort();
+        assert_eq!(all, rect(0..10, 0..10));
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::VecDeque;
+
+use libks::ScreenCoord;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{links::ScreenLink, partition::islands, screen_map::ScreenMap};
+use super::{Bounds, Partition, Partitioner, SizeLimit};
+
+/// Groups screens that the player can travel between by walking across screen edges, following
+/// warps or using shifts. Groups that don't fit in `max_size` are grown outward from their first
+/// screen until they can't get any bigger, and the rest is grouped again.
+#[derive(Default)]
+pub struct ConnectivityPartitioner {
+    pub max_size: SizeLimit,
+    pub links: Vec<ScreenLink>,
+    pub force: bool,
+}
+
+impl Partitioner for ConnectivityPartitioner {
+    fn partitions(&self, screens: &ScreenMap) -> Vec<Partition> {
+        let positions: Vec<_> = screens.iter_positions()
+            .copied()
+            .collect();
+        self.partition_clusters(&positions)
+    }
+}
+
+impl ConnectivityPartitioner {
+    fn partition_clusters(&self, positions: &[ScreenCoord]) -> Vec<Partition> {
+        let partition = Partition::new(positions.to_vec());
+        if !self.force
+            && !islands::is_partition_too_large(&partition, self.max_size)
+        {
+            return vec![partition];
+        }
+
+        let screens: FxHashSet<_> = positions.iter()
+            .copied()
+            .collect();
+        let mut links = FxHashMap::<ScreenCoord, Vec<ScreenCoord>>::default();
+        for link in &self.links {
+            if !screens.contains(&link.from) || !screens.contains(&link.to) {
+                continue;
+            }
+
+            // Clustering only cares whether two screens are connected, not which way
+            links.entry(link.from).or_default().push(link.to);
+            links.entry(link.to).or_default().push(link.from);
+        }
+
+        let mut partitions = Vec::new();
+        let mut visited = FxHashSet::default();
+        for pos in positions {
+            if visited.contains(pos) {
+                continue;
+            }
+
+            let positions = self.grow_cluster(&screens, &links, *pos, &mut visited);
+            partitions.push(Partition::new(positions));
+        }
+
+        partitions
+    }
+
+    /// Collects the screens reachable from `start` in breadth-first order, skipping any that would
+    /// make the cluster larger than `max_size`. Skipped screens are left for later clusters.
+    fn grow_cluster(
+        &self,
+        screens: &FxHashSet<ScreenCoord>,
+        links: &FxHashMap<ScreenCoord, Vec<ScreenCoord>>,
+        start: ScreenCoord,
+        visited: &mut FxHashSet<ScreenCoord>,
+    ) -> Vec<ScreenCoord> {
+        let mut cluster = vec![start];
+        let mut bounds = Bounds::from([start].as_slice());
+        let mut queue = VecDeque::from([start]);
+        visited.insert(start);
+
+        while let Some(pos) = queue.pop_front() {
+            let adjacent = [
+                (pos.0 - 1, pos.1),
+                (pos.0 + 1, pos.1),
+                (pos.0, pos.1 - 1),
+                (pos.0, pos.1 + 1),
+            ];
+            let linked = links.get(&pos)
+                .into_iter()
+                .flatten()
+                .copied();
+
+            for neighbor in adjacent.into_iter().chain(linked) {
+                if visited.contains(&neighbor) || !screens.contains(&neighbor) {
+                    continue;
+                }
+
+                let new_bounds = Bounds::union(&bounds, &Bounds::from([neighbor].as_slice()));
+                if !self.max_size.fits(&new_bounds) {
+                    continue;
+                }
+
+                bounds = new_bounds;
+                visited.insert(neighbor);
+                cluster.push(neighbor);
+                queue.push_back(neighbor);
+            }
+        }
+
+        cluster
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::links::LinkKind;
+    use super::*;
+
+    fn clusters(partitioner: &ConnectivityPartitioner, positions: &[ScreenCoord]) -> Vec<Vec<ScreenCoord>> {
+        let mut clusters: Vec<_> = partitioner.partition_clusters(positions)
+            .into_iter()
+            .map(|partition| {
+                let mut positions = partition.positions().to_vec();
+                positions.sort();
+                positions
+            })
+            .collect();
+        clusters.sort();
+        clusters
+    }
+
+    #[test]
+    fn links_join_screens_that_are_apart() {
+        let screens = [(0, 0), (1, 0), (5, 5), (9, 9)];
+        let partitioner = ConnectivityPartitioner {
+            force: true,
+            links: vec![
+                ScreenLink { from: (1, 0), to: (5, 5), kind: LinkKind::Shift('A') },
+                // Links to screens that don't exist are ignored
+                ScreenLink { from: (9, 9), to: (9, 10), kind: LinkKind::Warp('D') },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(clusters(&partitioner, &screens), vec![
+            vec![(0, 0), (1, 0), (5, 5)],
+            vec![(9, 9)],
+        ]);
+    }
+
+    #[test]
+    fn maps_that_fit_are_not_split_unless_forced() {
+        let screens = [(0, 0), (5, 5)];
+        let partitioner = ConnectivityPartitioner::default();
+        assert_eq!(clusters(&partitioner, &screens), vec![
+            vec![(0, 0), (5, 5)],
+        ]);
+
+        let partitioner = ConnectivityPartitioner {
+            force: true,
+            ..Default::default()
+        };
+        assert_eq!(clusters(&partitioner, &screens), vec![
+            vec![(0, 0)],
+            vec![(5, 5)],
+        ]);
+    }
+
+    #[test]
+    fn clusters_stop_growing_at_max_size() {
+        let screens: Vec<_> = (0..5)
+            .map(|x| (x, 0))
+            .collect();
+        let partitioner = ConnectivityPartitioner {
+            max_size: SizeLimit::new(2, 1),
+            ..Default::default()
+        };
+
+        assert_eq!(clusters(&partitioner, &screens), vec![
+            vec![(0, 0), (1, 0)],
+            vec![(2, 0), (3, 0)],
+            vec![(4, 0)],
+        ]);
+    }
+}
@@ -1,33 +1,22 @@
 use libks::ScreenCoord;
 
 use crate::screen_map::ScreenMap;
-use super::{Bounds, Partition, Partitioner};
+use super::{Bounds, Partition, Partitioner, SizeLimit};
 
+#[derive(Default)]
 pub struct GridPartitioner {
-    pub max_size: (u64, u64),
+    pub max_size: SizeLimit,
     pub rows: Option<u64>,
     pub cols: Option<u64>,
     pub force: bool,
 }
 
-impl Default for GridPartitioner {
-    fn default() -> Self {
-        Self {
-            max_size: (48000, 48000),
-            rows: None,
-            cols: None,
-            force: false,
-        }
-    }
-}
-
 impl Partitioner for GridPartitioner {
     fn partitions(&self, screens: &ScreenMap) -> Vec<Partition> {
         let bounds = Bounds::from_iter(screens.iter_positions());
         
         if !self.force
-            && bounds.width() <= self.max_size.0
-            && bounds.height() <= self.max_size.1
+            && self.max_size.fits(&bounds)
         {
             let positions: Vec<_> = screens.iter()
                 .map(|screen| screen.position)
@@ -35,8 +24,13 @@ impl Partitioner for GridPartitioner {
             return vec![Partition::new(positions)];
         }
         
-        let rows = self.rows.unwrap_or_else(|| calc_grid_rows(&bounds, self.max_size.1));
-        let cols = self.cols.unwrap_or_else(|| calc_grid_cols(&bounds, self.max_size.0));
+        let (rows, cols) = match (self.rows, self.cols) {
+            (None, None) => calc_grid_dimensions(&bounds, self.max_size),
+            (rows, cols) => (
+                rows.unwrap_or_else(|| calc_grid_rows(&bounds, self.max_size.max_height)),
+                cols.unwrap_or_else(|| calc_grid_cols(&bounds, self.max_size.max_width)),
+            ),
+        };
         let positions = screens.iter().map(|screen| &screen.position);
         
         partitions_from_grid(positions, &bounds, rows, cols)
@@ -54,9 +48,26 @@ pub fn calc_grid_cols(bounds: &Bounds, max_width: u64) -> u64 {
 }
 
 #[inline]
-pub fn calc_grid_dimensions(bounds: &Bounds, max_size: (u64, u64)) -> (u64, u64) {
-    let rows = calc_grid_rows(bounds, max_size.1);
-    let cols = calc_grid_cols(bounds, max_size.0);
+pub fn calc_grid_dimensions(bounds: &Bounds, max_size: SizeLimit) -> (u64, u64) {
+    let mut rows = u64::max(1, calc_grid_rows(bounds, max_size.max_height));
+    let mut cols = u64::max(1, calc_grid_cols(bounds, max_size.max_width));
+    
+    // Split the longer side of the cells in pixels until they're small enough
+    loop {
+        let cell_width = bounds.width().div_ceil(cols);
+        let cell_height = bounds.height().div_ceil(rows);
+        if cell_width * cell_height <= max_size.max_area || cell_width * cell_height <= 1 {
+            break;
+        }
+        
+        if cell_width > 1 && cell_width * 600 >= cell_height * 240 {
+            cols += 1;
+        }
+        else {
+            rows += 1;
+        }
+    }
+    
     (rows, cols)
 }
 
@@ -9,10 +9,10 @@ use libks::ScreenCoord;
 use rustc_hash::FxHashMap;
 
 use crate::{partition::{grid, merge_redundant_partitions}, screen_map::ScreenMap};
-use super::{Partition, Partitioner};
+use super::{Partition, Partitioner, SizeLimit};
 
 pub struct IslandsPartitioner {
-    pub max_size: (u64, u64),
+    pub max_size: SizeLimit,
     pub gap: RangeInclusive<u64>,
     pub force: bool,
 }
@@ -20,7 +20,7 @@ pub struct IslandsPartitioner {
 impl Default for IslandsPartitioner {
     fn default() -> Self {
         Self {
-            max_size: (48000, 48000),
+            max_size: SizeLimit::default(),
             gap: 1..=20,
             force: false,
         }
@@ -46,7 +46,7 @@ impl Partitioner for IslandsPartitioner {
     }
 }
 
-fn partition_recursively(partition: Partition, max_size: (u64, u64), min_gap: u64, max_gap: u64) -> Vec<Partition> {
+pub(super) fn partition_recursively(partition: Partition, max_size: SizeLimit, min_gap: u64, max_gap: u64) -> Vec<Partition> {
     let mut partitions = Vec::new();
 
     let graph = partition_into_graph(partition, max_gap);
@@ -73,9 +73,8 @@ fn partition_recursively(partition: Partition, max_size: (u64, u64), min_gap: u6
     partitions
 }
 
-fn is_partition_too_large(partition: &Partition, max_size: (u64, u64)) -> bool {
-    partition.bounds.width() > max_size.0
-        || partition.bounds.height() > max_size.1
+pub(super) fn is_partition_too_large(partition: &Partition, max_size: SizeLimit) -> bool {
+    !max_size.fits(&partition.bounds)
 }
 
 fn attenuate_max_gap(min_gap: u64, max_gap: u64) -> u64 {
@@ -88,31 +87,49 @@ fn attenuate_max_gap(min_gap: u64, max_gap: u64) -> u64 {
     }
 }
 
-fn partition_into_graph(partition: Partition, max_gap: u64) -> UnGraph<ScreenCoord, u64> {
+pub(super) fn partition_into_graph(partition: Partition, max_gap: u64) -> UnGraph<ScreenCoord, u64> {
     let n_screens = partition.len();
     let mut graph = UnGraph::with_capacity(n_screens, n_screens);
+    
+    // Bucket screens into square cells at least max_gap wide so that each screen only needs
+    // to be compared with the screens in its own cell and the eight cells around it
+    let cell_size = i64::try_from(max_gap)
+        .unwrap_or(i64::MAX)
+        .clamp(1, i64::from(u32::MAX));
+    let mut cells = FxHashMap::<(i64, i64), Vec<NodeIndex>>::default();
 
     for pos in partition {
         let node = graph.add_node(pos);
-
-        for other_node in graph.node_indices() {
-            let dist = {
-                let other_pos = &graph[other_node];
-                let dist_x = pos.0.abs_diff(other_pos.0) as u64;
-                let dist_y = pos.1.abs_diff(other_pos.1) as u64;
-                dist_x.saturating_add(dist_y)
-            };
-
-            if dist <= max_gap {
-                graph.add_edge(node, other_node, dist);
+        let cell_x = (pos.0 as i64).div_euclid(cell_size);
+        let cell_y = (pos.1 as i64).div_euclid(cell_size);
+
+        for neighbor_x in cell_x - 1..=cell_x + 1 {
+            for neighbor_y in cell_y - 1..=cell_y + 1 {
+                let Some(other_nodes) = cells.get(&(neighbor_x, neighbor_y)) else { continue };
+                for other_node in other_nodes {
+                    let dist = {
+                        let other_pos = &graph[*other_node];
+                        let dist_x = pos.0.abs_diff(other_pos.0) as u64;
+                        let dist_y = pos.1.abs_diff(other_pos.1) as u64;
+                        dist_x.saturating_add(dist_y)
+                    };
+
+                    if dist <= max_gap {
+                        graph.add_edge(node, *other_node, dist);
+                    }
+                }
             }
         }
+
+        cells.entry((cell_x, cell_y))
+            .or_default()
+            .push(node);
     }
 
     graph
 }
 
-fn graph_into_partitions(graph: UnGraph<ScreenCoord, u64>) -> Vec<Partition> {
+pub(super) fn graph_into_partitions(graph: UnGraph<ScreenCoord, u64>) -> Vec<Partition> {
     let mut vertex_sets = UnionFind::new(graph.node_bound());
     for edge in graph.edge_references() {
         vertex_sets.union(edge.source(), edge.target());
@@ -130,3 +147,73 @@ fn graph_into_partitions(graph: UnGraph<ScreenCoord, u64>) -> Vec<Partition> {
         .map(Partition::new)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+
+    /// The original O(n^2) implementation of `partition_into_graph`
+    fn partition_into_graph_naive(partition: Partition, max_gap: u64) -> UnGraph<ScreenCoord, u64> {
+        let n_screens = partition.len();
+        let mut graph = UnGraph::with_capacity(n_screens, n_screens);
+
+        for pos in partition {
+            let node = graph.add_node(pos);
+
+            for other_node in graph.node_indices() {
+                let dist = {
+                    let other_pos = &graph[other_node];
+                    let dist_x = pos.0.abs_diff(other_pos.0) as u64;
+                    let dist_y = pos.1.abs_diff(other_pos.1) as u64;
+                    dist_x.saturating_add(dist_y)
+                };
+
+                if dist <= max_gap {
+                    graph.add_edge(node, other_node, dist);
+                }
+            }
+        }
+
+        graph
+    }
+
+    fn sorted_islands(graph: UnGraph<ScreenCoord, u64>) -> Vec<Vec<ScreenCoord>> {
+        let mut islands: Vec<_> = graph_into_partitions(graph)
+            .into_iter()
+            .map(|partition| {
+                let mut positions = partition.positions().to_vec();
+                positions.sort();
+                positions
+            })
+            .collect();
+        islands.sort();
+        islands
+    }
+
+    #[test]
+    fn spatial_index_matches_naive_islands() {
+        let mut rng = SmallRng::seed_from_u64(0x151A_4D05);
+
+        for _ in 0..200 {
+            let n_screens = rng.random_range(0..150);
+            let spread = rng.random_range(1..60);
+            let origin: (i32, i32) = (rng.random_range(-1200..1200), rng.random_range(-1200..1200));
+            let mut positions: Vec<ScreenCoord> = (0..n_screens)
+                .map(|_| (
+                    origin.0 + rng.random_range(-spread..=spread),
+                    origin.1 + rng.random_range(-spread..=spread),
+                ))
+                .collect();
+            positions.sort();
+            positions.dedup();
+            positions.shuffle(&mut rng);
+            let max_gap = *[0, 1, 2, 3, 5, 10, 20, 100, u64::MAX].choose(&mut rng).unwrap();
+
+            let expected = sorted_islands(partition_into_graph_naive(Partition::new(positions.clone()), max_gap));
+            let actual = sorted_islands(partition_into_graph(Partition::new(positions), max_gap));
+            assert_eq!(expected, actual, "Islands differ with max gap {max_gap}");
+        }
+    }
+}
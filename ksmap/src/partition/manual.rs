@@ -0,0 +1,226 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use libks::ScreenCoord;
+use rustc_hash::FxHashSet;
+use serde::Deserialize;
+
+use crate::screen_map::ScreenMap;
+use super::{Bounds, Partition, Partitioner, SizeLimit, grid};
+
+/// A hand-written list of regions, loaded from a TOML file like this:
+///
+/// ```toml
+/// leftovers = "Other"
+///
+/// [[regions]]
+/// name = "Hub"
+/// rects = [{ from = [1000, 1000], to = [1004, 1002] }]
+/// screens = [[1005, 1001]]
+/// ```
+///
+/// Region names are used as file names, so they can't contain path separators, characters that
+/// Windows doesn't allow (`<>:"|?*` and control characters) or end with a dot or a space. They
+/// must be unique and can't be `other`, which is the name of the leftovers. They also can't contain
+/// `#`, which numbers the pieces of regions that are too large, e.g. `Hub #1` and `Hub #2`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Layout {
+    #[serde(default)]
+    pub leftovers: Leftovers,
+    #[serde(default)]
+    pub regions: Vec<Region>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Region {
+    pub name: String,
+    /// Rectangles of screens. Both corners are inclusive
+    #[serde(default)]
+    pub rects: Vec<Rect>,
+    #[serde(default)]
+    pub screens: Vec<ScreenCoord>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Rect {
+    pub from: ScreenCoord,
+    pub to: ScreenCoord,
+}
+
+/// What to do with screens that aren't in any region
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Leftovers {
+    /// Put them all in one partition named "other"
+    #[default]
+    Other,
+    /// Divide them with the fallback partitioner
+    Fallback,
+}
+
+pub struct ManualPartitioner {
+    pub layout: Layout,
+    /// Regions larger than this, including the leftovers, are split into numbered pieces
+    pub max_size: SizeLimit,
+    pub fallback: Option<Box<dyn Partitioner>>,
+}
+
+/// The name of the partition that holds screens outside every region
+const LEFTOVERS_NAME: &str = "other";
+/// Separates a region's name from the number of a piece of it. Region names can't contain it, so
+/// piece names can't be the same as a region's.
+const PIECE_SEPARATOR: char = '#';
+
+pub fn load_layout(path: impl AsRef<Path>) -> Result<Layout> {
+    let raw = fs::read_to_string(path)?;
+    let layout: Layout = toml::from_str(&raw)?;
+    check_region_names(&layout)?;
+    Ok(layout)
+}
+
+fn check_region_names(layout: &Layout) -> Result<()> {
+    let mut names = FxHashSet::default();
+    for region in &layout.regions {
+        let name = region.name.as_str();
+        let is_valid_file_name = !name.is_empty()
+            && !name.ends_with(['.', ' '])
+            && !name.contains(|c: char| c.is_control() || "/\\<>:\"|?*".contains(c));
+        if !is_valid_file_name {
+            return Err(anyhow!("Region name {name:?} can't be used as a file name"));
+        }
+        if name.contains(PIECE_SEPARATOR) {
+            return Err(anyhow!("Region name {name:?} can't contain {PIECE_SEPARATOR:?}, which is used to number the pieces of large regions"));
+        }
+        // Names are compared case-insensitively because some file systems are
+        let name = name.to_lowercase();
+        if name == LEFTOVERS_NAME {
+            return Err(anyhow!("Region name {:?} is reserved for screens outside every region", region.name));
+        }
+        if !names.insert(name) {
+            return Err(anyhow!("Region name {:?} is used more than once", region.name));
+        }
+    }
+    
+    Ok(())
+}
+
+impl Region {
+    pub fn contains(&self, pos: &ScreenCoord) -> bool {
+        self.screens.contains(pos)
+            || self.rects.iter().any(|rect| rect.contains(pos))
+    }
+}
+
+impl Rect {
+    pub fn contains(&self, pos: &ScreenCoord) -> bool {
+        let (min_x, max_x) = (i32::min(self.from.0, self.to.0), i32::max(self.from.0, self.to.0));
+        let (min_y, max_y) = (i32::min(self.from.1, self.to.1), i32::max(self.from.1, self.to.1));
+        (min_x..=max_x).contains(&pos.0)
+            && (min_y..=max_y).contains(&pos.1)
+    }
+}
+
+impl Partitioner for ManualPartitioner {
+    fn partitions(&self, screens: &ScreenMap) -> Vec<Partition> {
+        let mut region_positions = vec![Vec::new(); self.layout.regions.len()];
+        let mut leftovers = Vec::new();
+        
+        // Each screen goes to the first region that contains it
+        for pos in screens.iter_positions() {
+            match self.layout.regions.iter().position(|region| region.contains(pos)) {
+                Some(i) => region_positions[i].push(*pos),
+                None => leftovers.push(*pos),
+            }
+        }
+        
+        // The fallback sees the whole map, so only run it once and take out the screens each
+        // caller needs
+        let mut fallback_partitions = None;
+        let mut fallback_pieces = |positions: Vec<ScreenCoord>| -> Option<Vec<Vec<ScreenCoord>>> {
+            let fallback = self.fallback.as_ref()?;
+            let fallback_partitions = fallback_partitions.get_or_insert_with(|| fallback.partitions(screens));
+            let positions: FxHashSet<_> = positions.into_iter().collect();
+            let pieces = fallback_partitions.iter()
+                .map(|partition| partition.positions().iter()
+                    .filter(|pos| positions.contains(pos))
+                    .copied()
+                    .collect::<Vec<_>>())
+                .filter(|piece| !piece.is_empty())
+                .collect();
+            Some(pieces)
+        };
+        
+        let mut named: Vec<_> = self.layout.regions.iter()
+            .map(|region| region.name.clone())
+            .zip(region_positions)
+            .collect();
+        let mut leftover_partitions = Vec::new();
+        if !leftovers.is_empty() {
+            let fallback_leftovers = match self.layout.leftovers {
+                Leftovers::Fallback => fallback_pieces(leftovers.clone()),
+                Leftovers::Other => None,
+            };
+            match fallback_leftovers {
+                Some(pieces) => leftover_partitions.extend(pieces.into_iter().map(Partition::new)),
+                None => named.push((LEFTOVERS_NAME.to_owned(), leftovers)),
+            }
+        }
+        
+        let mut partitions = Vec::new();
+        for (name, positions) in named {
+            if positions.is_empty() {
+                continue;
+            }
+            
+            let bounds = Bounds::from(positions.as_slice());
+            if self.max_size.fits(&bounds) {
+                partitions.push(Partition::new(positions).with_name(name));
+                continue;
+            }
+            
+            // Split partitions that are too large like the rest of the map, or into a grid if
+            // there's no fallback
+            let pieces = fallback_pieces(positions.clone())
+                .unwrap_or_else(|| {
+                    let (rows, cols) = grid::calc_grid_dimensions(&bounds, self.max_size);
+                    grid::partitions_from_grid(positions.iter(), &bounds, rows, cols)
+                        .into_iter()
+                        .map(|partition| partition.positions().to_vec())
+                        .collect()
+                });
+            for (i, piece) in pieces.into_iter().enumerate() {
+                partitions.push(Partition::new(piece).with_name(format!("{name} {PIECE_SEPARATOR}{}", i + 1)));
+            }
+        }
+        partitions.extend(leftover_partitions);
+        
+        partitions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(names: &[&str]) -> Result<()> {
+        let regions = names.iter()
+            .map(|name| format!("[[regions]]\nname = {name:?}\n"))
+            .collect::<String>();
+        check_region_names(&toml::from_str(&regions).unwrap())
+    }
+
+    #[test]
+    fn region_names_must_be_file_names() {
+        assert!(check(&["Hub", "East Wing", "Level 1.5"]).is_ok());
+        for name in ["", ".", "..", "../Hub", "a/b", "a\\b", "Hub.", "Hub ", "a:b", "a*b", "a?b", "a\"b", "a<b", "a>b", "a|b", "a\tb"] {
+            assert!(check(&[name]).is_err(), "{name:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn region_names_must_be_unique() {
+        assert!(check(&["Hub", "hub"]).is_err());
+        assert!(check(&["Other"]).is_err());
+        // Pieces of a large "Hub" would be named like this
+        assert!(check(&["Hub", "Hub #1"]).is_err());
+    }
+}
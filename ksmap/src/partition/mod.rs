@@ -1,14 +1,24 @@
 mod bounds;
+mod compact;
+mod connectivity;
 mod grid;
 mod islands;
+mod manual;
+mod size_limit;
+mod zones;
 
 use libks::ScreenCoord;
 
 use crate::screen_map::ScreenMap;
 
 pub use bounds::Bounds;
+pub use compact::CompactPartitioner;
+pub use connectivity::ConnectivityPartitioner;
 pub use grid::GridPartitioner;
 pub use islands::IslandsPartitioner;
+pub use manual::{Layout, Leftovers, ManualPartitioner, Rect, Region, load_layout};
+pub use size_limit::SizeLimit;
+pub use zones::{ZoneKey, ZonePartitioner};
 
 pub trait Partitioner {
     fn partitions(&self, screens: &ScreenMap) -> Vec<Partition>;
@@ -18,6 +28,7 @@ pub trait Partitioner {
 pub struct Partition {
     positions: Vec<ScreenCoord>,
     bounds: Bounds,
+    name: Option<String>,
 }
 
 pub fn merge_redundant_partitions(partitions: &mut Vec<Partition>) {
@@ -56,8 +67,18 @@ impl Partition {
         Self {
             positions,
             bounds,
+            name: None,
         }
     }
+    
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = Some(name);
+        self
+    }
+    
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
 
     pub fn positions(&self) -> &[ScreenCoord] {
         &self.positions
@@ -0,0 +1,38 @@
+use super::Bounds;
+
+/// The largest partition a partitioner may produce, measured in screens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimit {
+    pub max_width: u64,
+    pub max_height: u64,
+    /// The largest width * height allowed, so that partitions can be long and thin as long as they
+    /// don't cover more screens than a squarer one would
+    pub max_area: u64,
+}
+
+impl SizeLimit {
+    pub fn new(max_width: u64, max_height: u64) -> Self {
+        Self {
+            max_width,
+            max_height,
+            max_area: u64::MAX,
+        }
+    }
+
+    pub fn with_max_area(mut self, max_area: u64) -> Self {
+        self.max_area = max_area;
+        self
+    }
+
+    pub fn fits(&self, bounds: &Bounds) -> bool {
+        bounds.width() <= self.max_width
+            && bounds.height() <= self.max_height
+            && bounds.width().saturating_mul(bounds.height()) <= self.max_area
+    }
+}
+
+impl Default for SizeLimit {
+    fn default() -> Self {
+        Self::new(48000, 48000)
+    }
+}
@@ -0,0 +1,217 @@
+use std::{collections::VecDeque, ops::RangeInclusive};
+
+use libks::{ScreenCoord, map_bin::{AssetId, ScreenData}};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{partition::islands, screen_map::ScreenMap};
+use super::{Partition, Partitioner, SizeLimit};
+
+/// An asset that screens must share to belong to the same zone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoneKey {
+    TilesetA,
+    TilesetB,
+    AtmosphereA,
+    AtmosphereB,
+    Music,
+    Gradient,
+}
+
+impl ZoneKey {
+    pub fn asset(self, screen: &ScreenData) -> AssetId {
+        match self {
+            ZoneKey::TilesetA => screen.assets.tileset_a,
+            ZoneKey::TilesetB => screen.assets.tileset_b,
+            ZoneKey::AtmosphereA => screen.assets.atmosphere_a,
+            ZoneKey::AtmosphereB => screen.assets.atmosphere_b,
+            ZoneKey::Music => screen.assets.music,
+            ZoneKey::Gradient => screen.assets.gradient,
+        }
+    }
+}
+
+/// Groups adjacent screens that share the same assets for every key, so that each image covers
+/// one area of the level. Zones that are too large are subdivided like islands.
+pub struct ZonePartitioner {
+    pub max_size: SizeLimit,
+    pub keys: Vec<ZoneKey>,
+    pub gap: RangeInclusive<u64>,
+    pub force: bool,
+}
+
+impl Default for ZonePartitioner {
+    fn default() -> Self {
+        Self {
+            max_size: SizeLimit::default(),
+            keys: vec![ZoneKey::TilesetA, ZoneKey::TilesetB],
+            gap: 1..=20,
+            force: false,
+        }
+    }
+}
+
+impl Partitioner for ZonePartitioner {
+    fn partitions(&self, screens: &ScreenMap) -> Vec<Partition> {
+        let keys: Vec<_> = screens.iter()
+            .map(|screen| (screen.position, self.zone_key(screen)))
+            .collect();
+        self.partition_zones(&keys)
+    }
+}
+
+impl ZonePartitioner {
+    fn zone_key(&self, screen: &ScreenData) -> Vec<AssetId> {
+        self.keys.iter()
+            .map(|key| key.asset(screen))
+            .collect()
+    }
+
+    fn partition_zones<K: PartialEq>(&self, screens: &[(ScreenCoord, K)]) -> Vec<Partition> {
+        let partition = Partition::new(screens.iter().map(|(pos, _)| *pos).collect());
+        if !self.force
+            && !islands::is_partition_too_large(&partition, self.max_size)
+        {
+            return vec![partition];
+        }
+
+        let mut partitions = Vec::new();
+        for zone in find_zones(screens) {
+            let partition = Partition::new(zone);
+            if islands::is_partition_too_large(&partition, self.max_size) {
+                let subpartitions = islands::partition_recursively(partition, self.max_size, *self.gap.start(), *self.gap.end());
+                partitions.extend(subpartitions);
+            }
+            else {
+                partitions.push(partition);
+            }
+        }
+
+        partitions
+    }
+}
+
+/// Groups the screens connected through edges between screens with the same key, in the order of
+/// their first screen
+fn find_zones<K: PartialEq>(screens: &[(ScreenCoord, K)]) -> Vec<Vec<ScreenCoord>> {
+    let keys: FxHashMap<_, _> = screens.iter()
+        .map(|(pos, key)| (*pos, key))
+        .collect();
+    let mut zones = Vec::new();
+    let mut visited = FxHashSet::default();
+
+    for (start, key) in screens {
+        if !visited.insert(*start) {
+            continue;
+        }
+
+        let mut zone = vec![*start];
+        let mut queue = VecDeque::from([*start]);
+        while let Some(pos) = queue.pop_front() {
+            let neighbors = [
+                (pos.0 - 1, pos.1),
+                (pos.0 + 1, pos.1),
+                (pos.0, pos.1 - 1),
+                (pos.0, pos.1 + 1),
+            ];
+            for neighbor_pos in neighbors {
+                if keys.get(&neighbor_pos) != Some(&key) || !visited.insert(neighbor_pos) {
+                    continue;
+                }
+
+                zone.push(neighbor_pos);
+                queue.push_back(neighbor_pos);
+            }
+        }
+        zones.push(zone);
+    }
+
+    zones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zones<K: PartialEq>(partitioner: &ZonePartitioner, screens: &[(ScreenCoord, K)]) -> Vec<Vec<ScreenCoord>> {
+        let mut zones: Vec<_> = partitioner.partition_zones(screens)
+            .into_iter()
+            .map(|partition| {
+                let mut positions = partition.positions().to_vec();
+                positions.sort();
+                positions
+            })
+            .collect();
+        zones.sort();
+        zones
+    }
+
+    fn forced() -> ZonePartitioner {
+        ZonePartitioner {
+            force: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn adjacent_screens_with_the_same_key_share_a_zone() {
+        let screens = [
+            ((0, 0), (1, 2)),
+            ((1, 0), (1, 2)),
+            ((1, 1), (1, 2)),
+            ((2, 0), (1, 3)),
+        ];
+
+        assert_eq!(zones(&forced(), &screens), vec![
+            vec![(0, 0), (1, 0), (1, 1)],
+            vec![(2, 0)],
+        ]);
+    }
+
+    #[test]
+    fn zones_do_not_jump_gaps() {
+        let screens = [
+            ((0, 0), 1),
+            ((2, 0), 1),
+            ((1, 1), 1),
+        ];
+
+        assert_eq!(zones(&forced(), &screens), vec![
+            vec![(0, 0)],
+            vec![(1, 1)],
+            vec![(2, 0)],
+        ]);
+    }
+
+    #[test]
+    fn maps_that_fit_are_not_split_unless_forced() {
+        let screens = [
+            ((0, 0), 1),
+            ((1, 0), 2),
+        ];
+
+        assert_eq!(zones(&ZonePartitioner::default(), &screens), vec![
+            vec![(0, 0), (1, 0)],
+        ]);
+        assert_eq!(zones(&forced(), &screens), vec![
+            vec![(0, 0)],
+            vec![(1, 0)],
+        ]);
+    }
+
+    #[test]
+    fn large_zones_are_subdivided() {
+        let screens: Vec<_> = (0..6)
+            .map(|x| ((x, 0), 1))
+            .collect();
+        let partitioner = ZonePartitioner {
+            max_size: SizeLimit::new(2, 1),
+            ..Default::default()
+        };
+
+        let zones = zones(&partitioner, &screens);
+        assert_eq!(zones.concat().len(), 6);
+        for zone in zones {
+            assert!(partitioner.max_size.fits(&zone.as_slice().into()), "{zone:?} is too large");
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use libks::ScreenCoord;
+use libks_ini::Ini;
+use petgraph::{prelude::*, visit::{EdgeRef, IntoNodeReferences}};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Serialize;
+
+use crate::{
+    links::{self, LinkKind},
+    screen_map::ScreenMap,
+};
+
+/// How the player gets from one screen to another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScreenEdge {
+    /// Walking across the edge of the screen into its neighbor, labelled `U`, `D`, `L` or `R`
+    Walk(char),
+    Link(LinkKind),
+}
+
+impl ScreenEdge {
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            ScreenEdge::Walk(_) => "walk",
+            ScreenEdge::Link(LinkKind::Shift(_)) => "shift",
+            ScreenEdge::Link(LinkKind::Warp(_)) => "warp",
+        }
+    }
+
+    pub fn label(&self) -> char {
+        match self {
+            ScreenEdge::Walk(label)
+            | ScreenEdge::Link(LinkKind::Shift(label))
+            | ScreenEdge::Link(LinkKind::Warp(label)) => *label,
+        }
+    }
+}
+
+pub type ScreenGraph = DiGraph<ScreenCoord, ScreenEdge>;
+
+/// Builds a graph of the screens in the map, with an edge for every neighbor the player can walk
+/// into and every shift and warp that leads to an existing screen. Walking doesn't take walls into
+/// account, and a warp replaces walking in its direction.
+pub fn build_screen_graph(screens: &ScreenMap, ini: &Ini) -> ScreenGraph {
+    let mut graph = ScreenGraph::new();
+    let mut nodes = FxHashMap::default();
+    for pos in screens.iter_positions() {
+        nodes.insert(*pos, graph.add_node(*pos));
+    }
+
+    let links = links::find_screen_links(screens, ini);
+    let warps: FxHashSet<_> = links.iter()
+        .filter(|link| matches!(link.kind, LinkKind::Warp(_)))
+        .map(|link| (link.from, link.kind))
+        .collect();
+
+    for pos in screens.iter_positions() {
+        for (dir, step) in [('U', (0, -1)), ('D', (0, 1)), ('L', (-1, 0)), ('R', (1, 0))] {
+            let Some(neighbor) = nodes.get(&(pos.0 + step.0, pos.1 + step.1)) else { continue };
+            if !warps.contains(&(*pos, LinkKind::Warp(dir))) {
+                graph.add_edge(nodes[pos], *neighbor, ScreenEdge::Walk(dir));
+            }
+        }
+    }
+
+    for link in links {
+        let (Some(from), Some(to)) = (nodes.get(&link.from), nodes.get(&link.to)) else { continue };
+        graph.add_edge(*from, *to, ScreenEdge::Link(link.kind));
+    }
+
+    graph
+}
+
+fn node_id(pos: &ScreenCoord) -> String {
+    format!("x{}y{}", pos.0, pos.1)
+}
+
+/// Formats the graph for Graphviz. Screens are positioned as they are in the map for `neato -n`.
+pub fn to_dot(graph: &ScreenGraph) -> String {
+    let mut dot = String::from("digraph screens {\n");
+
+    for (_, pos) in graph.node_references() {
+        let _ = writeln!(dot, "    \"{}\" [pos=\"{},{}\"];", node_id(pos), pos.0 * 100, -pos.1 * 40);
+    }
+
+    for edge in graph.edge_references() {
+        let color = match edge.weight() {
+            ScreenEdge::Walk(_) => "black",
+            ScreenEdge::Link(LinkKind::Shift(_)) => "blue",
+            ScreenEdge::Link(LinkKind::Warp(_)) => "red",
+        };
+        let _ = writeln!(
+            dot,
+            "    \"{}\" -> \"{}\" [kind={}, label=\"{}\", color={color}];",
+            node_id(&graph[edge.source()]),
+            node_id(&graph[edge.target()]),
+            edge.weight().kind_name(),
+            edge.weight().label(),
+        );
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+pub fn to_graphml(graph: &ScreenGraph) -> String {
+    let mut xml = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        "  <key id=\"x\" for=\"node\" attr.name=\"x\" attr.type=\"int\"/>\n",
+        "  <key id=\"y\" for=\"node\" attr.name=\"y\" attr.type=\"int\"/>\n",
+        "  <key id=\"kind\" for=\"edge\" attr.name=\"kind\" attr.type=\"string\"/>\n",
+        "  <key id=\"label\" for=\"edge\" attr.name=\"label\" attr.type=\"string\"/>\n",
+        "  <graph id=\"screens\" edgedefault=\"directed\">\n",
+    ));
+
+    for (_, pos) in graph.node_references() {
+        let _ = writeln!(
+            xml,
+            "    <node id=\"{}\"><data key=\"x\">{}</data><data key=\"y\">{}</data></node>",
+            node_id(pos),
+            pos.0,
+            pos.1,
+        );
+    }
+
+    for edge in graph.edge_references() {
+        let _ = writeln!(
+            xml,
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"kind\">{}</data><data key=\"label\">{}</data></edge>",
+            node_id(&graph[edge.source()]),
+            node_id(&graph[edge.target()]),
+            edge.weight().kind_name(),
+            edge.weight().label(),
+        );
+    }
+
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphJson {
+    pub nodes: Vec<NodeJson>,
+    pub edges: Vec<EdgeJson>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeJson {
+    pub id: String,
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EdgeJson {
+    pub from: String,
+    pub to: String,
+    pub kind: &'static str,
+    pub label: char,
+}
+
+pub fn to_json(graph: &ScreenGraph) -> GraphJson {
+    GraphJson {
+        nodes: graph.node_references()
+            .map(|(_, pos)| NodeJson {
+                id: node_id(pos),
+                x: pos.0,
+                y: pos.1,
+            })
+            .collect(),
+        edges: graph.edge_references()
+            .map(|edge| EdgeJson {
+                from: node_id(&graph[edge.source()]),
+                to: node_id(&graph[edge.target()]),
+                kind: edge.weight().kind_name(),
+                label: edge.weight().label(),
+            })
+            .collect(),
+    }
+}
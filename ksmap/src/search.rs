@@ -0,0 +1,122 @@
+use std::ops::RangeInclusive;
+
+use libks::{ScreenCoord, constants::SCREEN_WIDTH, map_bin::{LayerData, Tile}};
+use serde::Serialize;
+
+use crate::{
+    definitions::{ObjectDefs, ObjectKind},
+    id::ObjectId,
+    screen_map::ScreenMap,
+};
+
+/// The layers that hold tiles from tilesets A and B
+pub const TILE_LAYERS: RangeInclusive<usize> = 0..=3;
+/// The layers that hold objects
+pub const OBJECT_LAYERS: RangeInclusive<usize> = 4..=7;
+
+/// What to look for. On tile layers, the bank is the tileset (0 for A, 1 for B) and the index is
+/// the tile's position in the tileset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileQuery {
+    Tile(Tile),
+    /// Any object in the bank, or any tile from the tileset
+    Bank(u8),
+}
+
+impl TileQuery {
+    pub fn matches(self, tile: Tile) -> bool {
+        if tile.1 == 0 {
+            return false;
+        }
+
+        match self {
+            TileQuery::Tile(query) => tile == query,
+            TileQuery::Bank(bank) => tile.0 == bank,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub target: TileQuery,
+    /// The layers to search, by default the object layers
+    pub layers: Vec<usize>,
+    /// Also match custom objects that override a matching object
+    pub match_originals: bool,
+}
+
+impl SearchQuery {
+    pub fn new(target: TileQuery) -> Self {
+        Self {
+            target,
+            layers: OBJECT_LAYERS.collect(),
+            match_originals: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    /// The object or tile placed in the map
+    pub object: String,
+    /// The object that a custom object overrides, if it matched through it
+    pub original: Option<String>,
+    pub screen: ScreenCoord,
+    pub layer: usize,
+    /// The index of the tile in the layer, counting left to right and top to bottom
+    pub tile: usize,
+}
+
+impl SearchMatch {
+    /// The column and row of the tile within the screen
+    pub fn tile_position(&self) -> (usize, usize) {
+        (self.tile % SCREEN_WIDTH, self.tile / SCREEN_WIDTH)
+    }
+}
+
+/// Lists every place where the query matches, ordered by screen from top to bottom and left to
+/// right, then by layer and tile
+pub fn find_tiles(screens: &ScreenMap, query: &SearchQuery, defs: &ObjectDefs) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+
+    for screen in screens.iter() {
+        for &index_layer in &query.layers {
+            let Some(LayerData(layer)) = screen.layers.get(index_layer) else { continue };
+            let is_object_layer = OBJECT_LAYERS.contains(&index_layer);
+
+            for (index_tile, tile) in layer.iter().enumerate() {
+                if tile.1 == 0 { continue }
+
+                let original = if is_object_layer && query.match_originals {
+                    match defs.get(&ObjectId::from(tile)).map(|def| def.kind) {
+                        Some(ObjectKind::OverrideObject(original)) => Some(original),
+                        _ => None,
+                    }
+                }
+                else {
+                    None
+                };
+                let original = original.filter(|original| query.target.matches(*original));
+                if !query.target.matches(*tile) && original.is_none() {
+                    continue;
+                }
+
+                matches.push(SearchMatch {
+                    object: ObjectId::from(tile).to_string(),
+                    original: original.map(|original| ObjectId::from(original).to_string()),
+                    screen: screen.position,
+                    layer: index_layer,
+                    tile: index_tile,
+                });
+            }
+        }
+    }
+
+    matches.sort_by_key(|found| (
+        found.screen.1,
+        found.screen.0,
+        found.layer,
+        found.tile,
+    ));
+    matches
+}
@@ -26,6 +26,8 @@ pub enum RngStep {
     Flip = 7,
     Alpha = 8,
     ElementalVariant = 9,
+    // Synchronization (cont.)
+    WorldAnimationTime = 10,
 }
 
 pub struct SeedHasher(FxHasher);
@@ -3,29 +3,48 @@ use std::hash::{Hash, Hasher};
 use rustc_hash::{FxHashMap, FxHasher};
 use petgraph::unionfind::UnionFind;
 use rand::prelude::*;
-use libks::{constants::{SCREEN_WIDTH, TILES_PER_LAYER}, map_bin::{LayerData, ScreenData}};
+use libks::{ScreenCoord, constants::{SCREEN_WIDTH, TILES_PER_LAYER}, map_bin::{LayerData, ScreenData, Tile}};
+use serde::Serialize;
 
 use crate::{
     analysis::count_laser_phases,
-    definitions::{LaserPhase, Limit, ObjectDefs, ObjectKind},
+    definitions::{LaserPhase, Limit, LimitScope, ObjectDefs, ObjectKind},
     id::ObjectId,
     screen_map::ScreenMap,
     seed::{MapSeed, RngStep},
 };
 
 pub struct WorldSync {
+    pub anim_t: u32,
     pub groups: Vec<GroupSync>,
+    /// The sync group each screen belongs to, indexed by screen
+    pub group_ids: Vec<usize>,
+    /// The screens in each sync group, indexed by group ID and sorted by position
+    pub group_members: Vec<Vec<usize>>,
+    /// The instances chosen by group- and world-scoped limits, indexed by screen.
+    /// Instances are numbered in drawing order within each screen.
+    pub scoped_limits: Vec<FxHashMap<ObjectId, Vec<usize>>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncGroupInfo {
+    pub id: usize,
+    pub anim_t: u32,
+    pub laser_phase: Option<LaserPhase>,
+    pub screens: Vec<ScreenCoord>,
 }
 
 #[derive(Clone, Copy, Default)]
 pub struct GroupSync {
     pub anim_t: u32,
-    pub laser_phase: LaserPhase,
+    /// The laser phase that is visible, or `None` if both phases are drawn
+    pub laser_phase: Option<LaserPhase>,
 }
 
 pub struct ScreenSync {
     pub group: GroupSync,
     pub anim_t: u32,
+    pub world_anim_t: u32,
     pub limiters: FxHashMap<ObjectId, Limiter>,
 }
 
@@ -35,69 +54,118 @@ pub struct Limiter {
 }
 
 pub struct SyncOptions {
-    pub maximize_visible_lasers: bool,
+    pub laser_strategy: LaserStrategy,
+    /// Also join screens whose corner objects touch diagonally
+    pub sync_diagonals: bool,
 }
 
 impl Default for SyncOptions {
     fn default() -> Self {
-        Self { maximize_visible_lasers: true }
+        Self {
+            laser_strategy: LaserStrategy::Maximize,
+            sync_diagonals: false,
+        }
+    }
+}
+
+/// How to pick the laser phase (red/green) that is visible in each sync group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LaserStrategy {
+    /// Pick the phase with the most lasers in each group
+    #[default]
+    Maximize,
+    /// Pick a random phase for each group
+    Random,
+    /// Pick the phase with the most lasers in the whole world and use it for every group
+    WorldConsistent,
+    /// Show red lasers in every group
+    ForceRed,
+    /// Show green lasers in every group
+    ForceGreen,
+    /// Draw both phases with transparency
+    Overlay,
+}
+
+const TOP_LEFT: usize = 0;
+const TOP_RIGHT: usize = SCREEN_WIDTH - 1;
+const BOTTOM_LEFT: usize = TILES_PER_LAYER - SCREEN_WIDTH;
+const BOTTOM_RIGHT: usize = TILES_PER_LAYER - 1;
+const OFFSET_NORTH_TO_SOUTH: usize = BOTTOM_LEFT - TOP_LEFT;
+const OFFSET_WEST_TO_EAST: usize = TOP_RIGHT - TOP_LEFT;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    fn opposite(self) -> Self {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
     }
 }
 
+/// The sides of a screen whose borders are checked, so every border is only checked once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Border {
+    North,
+    West,
+}
+
 impl WorldSync {
     pub fn new(seed: MapSeed, screens: &ScreenMap, object_defs: &ObjectDefs, options: &SyncOptions) -> Self {
-        const TOP_LEFT: usize = 0;
-        const TOP_RIGHT: usize = SCREEN_WIDTH - 1;
-        const BOTTOM_LEFT: usize = TILES_PER_LAYER - SCREEN_WIDTH;
-        const _BOTTOM_RIGHT: usize = TILES_PER_LAYER - 1;
-        const OFFSET_NORTH_TO_SOUTH: usize = BOTTOM_LEFT - TOP_LEFT;
-        const OFFSET_WEST_TO_EAST: usize = TOP_RIGHT - TOP_LEFT;
-        
         let mut uf = UnionFind::<usize>::new(screens.len());
         
         for (index_current, screen) in screens.iter().enumerate() {
+            let (x, y) = screen.position;
+            
             // Northern border
-            if let Some(index_north) = screens.index_of(&(screen.position.0, screen.position.1 - 1)) {
-                let screen_north = &screens[index_north];
-                'north: for LayerData(layer) in &screen.layers {
-                    for i in TOP_LEFT..=TOP_RIGHT {
-                        let id = ObjectId::from(layer[i]);
-                        let Some(def) = object_defs.get(&id) else { continue };
-                        let j = i + OFFSET_NORTH_TO_SOUTH;
-                        for ObjectId(sync_tile, _) in &def.sync_params.sync_north {
-                            if screen_north.layers[4].0[j] == *sync_tile
-                                || screen_north.layers[5].0[j] == *sync_tile
-                                || screen_north.layers[6].0[j] == *sync_tile
-                                || screen_north.layers[7].0[j] == *sync_tile
-                            {
-                                uf.union(index_current, index_north);
-                                break 'north;
-                            }
-                        }
-                    }
-                }
+            if let Some(index_north) = screens.index_of(&(x, y - 1))
+                && borders_sync(screen, &screens[index_north], Border::North, object_defs)
+            {
+                uf.union(index_current, index_north);
             }
             
             // Western border
-            if let Some(index_west) = screens.index_of(&(screen.position.0 - 1, screen.position.1)) {
-                let screen_west = &screens[index_west];
-                'west: for LayerData(layer) in &screen.layers {
-                    for i in (TOP_LEFT..=BOTTOM_LEFT).step_by(SCREEN_WIDTH) {
-                        let id = ObjectId::from(layer[i]);
-                        let Some(def) = object_defs.get(&id) else { continue };
-                        let j = i + OFFSET_WEST_TO_EAST;
-                        for ObjectId(sync_tile, _) in &def.sync_params.sync_west {
-                            if screen_west.layers[4].0[j] == *sync_tile
-                                || screen_west.layers[5].0[j] == *sync_tile
-                                || screen_west.layers[6].0[j] == *sync_tile
-                                || screen_west.layers[7].0[j] == *sync_tile
-                            {
-                                uf.union(index_current, index_west);
-                                break 'west;
-                            }
-                        }
-                    }
-                }
+            if let Some(index_west) = screens.index_of(&(x - 1, y))
+                && borders_sync(screen, &screens[index_west], Border::West, object_defs)
+            {
+                uf.union(index_current, index_west);
+            }
+            
+            if !options.sync_diagonals {
+                continue;
+            }
+            
+            // North-western corner
+            if let Some(index_north_west) = screens.index_of(&(x - 1, y - 1))
+                && cells_sync(
+                    screen, TOP_LEFT,
+                    &screens[index_north_west], BOTTOM_RIGHT,
+                    &[Direction::North, Direction::West],
+                    object_defs,
+                )
+            {
+                uf.union(index_current, index_north_west);
+            }
+            
+            // North-eastern corner
+            if let Some(index_north_east) = screens.index_of(&(x + 1, y - 1))
+                && cells_sync(
+                    screen, TOP_RIGHT,
+                    &screens[index_north_east], BOTTOM_LEFT,
+                    &[Direction::North, Direction::East],
+                    object_defs,
+                )
+            {
+                uf.union(index_current, index_north_east);
             }
         }
         
@@ -108,15 +176,43 @@ impl WorldSync {
             members.push(index_member);
         }
         
+        // Number the groups in order of their top-left screen so IDs are stable between runs
+        let mut group_members: Vec<_> = groups_by_rep.into_values().collect();
+        for members in &mut group_members {
+            members.sort_by(|i, j| {
+                screens[*i].position.cmp(&screens[*j].position)
+            });
+        }
+        group_members.sort_by(|a, b| {
+            screens[a[0]].position.cmp(&screens[b[0]].position)
+        });
+        let mut group_ids = vec![0; screens.len()];
+        
+        let has_scoped_limits = object_defs.values()
+            .any(|def| def.limit.scope() != LimitScope::Screen);
+        let limited_counts: Vec<_> = if has_scoped_limits {
+            screens.iter()
+                .map(|screen| count_limited_objects(screen, object_defs))
+                .collect()
+        }
+        else {
+            Vec::new()
+        };
+        let mut scoped_limits = vec![FxHashMap::default(); screens.len()];
+        
         let mut group_syncs = vec![GroupSync::default(); screens.len()];
         let laser_phases = count_laser_phases(screens, object_defs);
-        for mut members in groups_by_rep.into_values() {
+        let world_laser_phase = match options.laser_strategy {
+            LaserStrategy::WorldConsistent => {
+                let all_screens: Vec<_> = (0..screens.len()).collect();
+                pick_laser_phase(seed, 0, &laser_phases, &all_screens, LaserStrategy::Maximize)
+            },
+            _ => None,
+        };
+        for (group_id, members) in group_members.iter().enumerate() {
             let group_hash = {
                 let mut hasher = FxHasher::default();
-                members.sort_by(|i, j| {
-                    screens[*i].position.cmp(&screens[*j].position)
-                });
-                for index_member in &members {
+                for index_member in members {
                     screens[*index_member].position.hash(&mut hasher);
                 }
                 hasher.finish()
@@ -125,21 +221,210 @@ impl WorldSync {
             let anim_t = seed.hasher(RngStep::GroupAnimationTime)
                 .write(group_hash)
                 .next_u32();
-            let laser_phase = pick_laser_phase(seed, group_hash, &laser_phases, &members, options.maximize_visible_lasers);           
+            let laser_phase = match world_laser_phase {
+                Some(phase) => Some(phase),
+                None => pick_laser_phase(seed, group_hash, &laser_phases, members, options.laser_strategy),
+            };
             let group_sync = GroupSync {
                 anim_t,
                 laser_phase,
             };
             
+            if has_scoped_limits {
+                choose_scoped_limits(seed, LimitScope::Group, Some(group_hash), members, &limited_counts, object_defs, &mut scoped_limits);
+            }
+            
             for index_member in members {
-                group_syncs[index_member] = group_sync;
+                group_syncs[*index_member] = group_sync;
+                group_ids[*index_member] = group_id;
             }
         }
         
+        if has_scoped_limits {
+            let mut all_screens: Vec<_> = (0..screens.len()).collect();
+            all_screens.sort_by(|i, j| {
+                screens[*i].position.cmp(&screens[*j].position)
+            });
+            choose_scoped_limits(seed, LimitScope::World, None, &all_screens, &limited_counts, object_defs, &mut scoped_limits);
+        }
+        
+        let anim_t = seed.hasher(RngStep::WorldAnimationTime)
+            .next_u32();
+        
         Self {
+            anim_t,
             groups: group_syncs,
+            group_ids,
+            group_members,
+            scoped_limits,
+        }
+    }
+    
+    /// Summarizes each sync group for debugging and export
+    pub fn describe_groups(&self, screens: &ScreenMap) -> Vec<SyncGroupInfo> {
+        self.group_members.iter()
+            .enumerate()
+            .map(|(id, members)| {
+                let sync = self.groups[members[0]];
+                SyncGroupInfo {
+                    id,
+                    anim_t: sync.anim_t,
+                    laser_phase: sync.laser_phase,
+                    screens: members.iter()
+                        .map(|index_member| screens[*index_member].position)
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Checks whether any pair of objects facing each other across the border between `screen` and
+/// `neighbor` are synchronized. `border` is the side of `screen` that `neighbor` is on.
+fn borders_sync(screen: &ScreenData, neighbor: &ScreenData, border: Border, object_defs: &ObjectDefs) -> bool {
+    match border {
+        Border::North => (TOP_LEFT..=TOP_RIGHT).any(|i| {
+            cells_sync(screen, i, neighbor, i + OFFSET_NORTH_TO_SOUTH, &[Direction::North], object_defs)
+        }),
+        Border::West => (TOP_LEFT..=BOTTOM_LEFT).step_by(SCREEN_WIDTH).any(|i| {
+            cells_sync(screen, i, neighbor, i + OFFSET_WEST_TO_EAST, &[Direction::West], object_defs)
+        }),
+    }
+}
+
+/// Checks whether an object at index `i` of `screen` is synchronized with an object at index `j`
+/// of `neighbor`. Either object's sync list may establish the link, so definitions don't need to
+/// be symmetric.
+///
+/// The side whose sync list is used is checked on every layer, while the object it lists is only
+/// looked for on the object layers (4-7) of the other side.
+fn cells_sync(
+    screen: &ScreenData,
+    i: usize,
+    neighbor: &ScreenData,
+    j: usize,
+    directions: &[Direction],
+    object_defs: &ObjectDefs,
+) -> bool {
+    for (index_layer, LayerData(layer)) in screen.layers.iter().enumerate() {
+        let tile = layer[i];
+        if tile.1 == 0 { continue }
+        
+        for (index_neighbor_layer, LayerData(neighbor_layer)) in neighbor.layers.iter().enumerate() {
+            let neighbor_tile = neighbor_layer[j];
+            if neighbor_tile.1 == 0 { continue }
+            
+            for direction in directions {
+                if (index_neighbor_layer >= 4 && lists_sync(object_defs, tile, *direction, neighbor_tile))
+                    || (index_layer >= 4 && lists_sync(object_defs, neighbor_tile, direction.opposite(), tile))
+                {
+                    return true;
+                }
+            }
         }
     }
+    
+    false
+}
+
+/// Checks whether `tile` lists `other` as an object it syncs with in the given direction.
+fn lists_sync(object_defs: &ObjectDefs, tile: Tile, direction: Direction, other: Tile) -> bool {
+    let Some(def) = object_defs.get(&ObjectId::from(tile)) else {
+        return false;
+    };
+    let sync_list = match direction {
+        Direction::North => &def.sync_params.sync_north,
+        Direction::South => &def.sync_params.sync_south,
+        Direction::East => &def.sync_params.sync_east,
+        Direction::West => &def.sync_params.sync_west,
+    };
+    sync_list.iter().any(|ObjectId(sync_tile, _)| *sync_tile == other)
+}
+
+/// Picks which instances of each object limited at `scope` are drawn across `members`, which must
+/// be sorted. The chosen instances are split up and stored per screen in `scoped_limits`.
+fn choose_scoped_limits(
+    seed: MapSeed,
+    scope: LimitScope,
+    group_hash: Option<u64>,
+    members: &[usize],
+    limited_counts: &[FxHashMap<ObjectId, usize>],
+    object_defs: &ObjectDefs,
+    scoped_limits: &mut [FxHashMap<ObjectId, Vec<usize>>],
+) {
+    let mut totals = FxHashMap::<ObjectId, usize>::default();
+    for index_member in members {
+        for (id, count) in &limited_counts[*index_member] {
+            if object_defs.get(id).is_some_and(|def| def.limit.scope() == scope) {
+                *totals.entry(*id).or_insert(0) += count;
+            }
+        }
+    }
+    
+    for (id, total) in totals {
+        let Some(def) = object_defs.get(&id) else { continue };
+        let mut hasher = seed.hasher(RngStep::Limiters)
+            .write(scope);
+        if let Some(group_hash) = group_hash {
+            hasher = hasher.write(group_hash);
+        }
+        let mut rng = hasher.write(id).into_rng();
+        
+        let mut chosen = match def.limit {
+            Limit::None => continue,
+            Limit::First { n, .. } => Vec::from_iter(0..n.min(total)),
+            Limit::Random { n, .. } => choose_n_indices(&mut rng, total, n),
+            Limit::LogNPlusOne { .. } => choose_n_indices(&mut rng, total, log_n_plus_one(total)),
+        };
+        chosen.sort_unstable();
+        
+        // Translate the chosen instances into indices local to each screen
+        let mut chosen = chosen.into_iter().peekable();
+        let mut first_instance = 0;
+        for index_member in members {
+            let count = limited_counts[*index_member].get(&id).copied().unwrap_or(0);
+            if count == 0 { continue }
+            
+            let end = first_instance + count;
+            let mut chosen_local = Vec::new();
+            while let Some(instance) = chosen.next_if(|instance| *instance < end) {
+                chosen_local.push(instance - first_instance);
+            }
+            scoped_limits[*index_member].insert(id, chosen_local);
+            first_instance = end;
+        }
+    }
+}
+
+/// Counts the objects on a screen that have a limit, keyed by the ID the limit applies to
+fn count_limited_objects(screen: &ScreenData, object_defs: &ObjectDefs) -> FxHashMap<ObjectId, usize> {
+    let mut counts = FxHashMap::default();
+    
+    for LayerData(layer) in &screen.layers[4..] {
+        for tile in layer {
+            if tile.1 > 0
+                && let Some(def) = object_defs.get(&ObjectId::from(tile))
+                && def.limit != Limit::None
+            {
+                let id = match &def.kind {
+                    ObjectKind::OverrideObject(tile_original) => ObjectId::from(tile_original),
+                    _ => ObjectId::from(tile),
+                };
+                counts.entry(id)
+                    .and_modify(|count| *count += 1)
+                    .or_insert(1);
+            }
+        }
+    }
+    
+    counts
+}
+
+fn log_n_plus_one(count: usize) -> usize {
+    (1.0 + (count as f32).log2())
+        .round()
+        .clamp(0.0, count as f32)
+        as usize
 }
 
 fn pick_laser_phase(
@@ -147,8 +432,16 @@ fn pick_laser_phase(
     group_hash: u64,
     phase_counts: &[[usize; 2]],
     members: &[usize],
-    maximize: bool
-) -> LaserPhase {
+    strategy: LaserStrategy,
+) -> Option<LaserPhase> {
+    let maximize = match strategy {
+        LaserStrategy::Maximize | LaserStrategy::WorldConsistent => true,
+        LaserStrategy::Random => false,
+        LaserStrategy::ForceRed => return Some(LaserPhase::Red),
+        LaserStrategy::ForceGreen => return Some(LaserPhase::Green),
+        LaserStrategy::Overlay => return None,
+    };
+    
     let mut total_red = 0;
     let mut total_green = 0;
     
@@ -157,7 +450,7 @@ fn pick_laser_phase(
         total_green += phase_counts[*index_member][LaserPhase::Green as usize];
     }
     
-    if total_green == 0 || (maximize && total_red > total_green) {
+    let phase = if total_green == 0 || (maximize && total_red > total_green) {
         LaserPhase::Red
     }
     else if total_red == 0 || (maximize && total_green > total_red) {
@@ -168,56 +461,46 @@ fn pick_laser_phase(
             .write(group_hash)
             .into_rng();
         *[LaserPhase::Red, LaserPhase::Green].choose(&mut rng).unwrap()
-    }
+    };
+    
+    Some(phase)
 }
 
 impl ScreenSync {
-    pub fn new(seed: MapSeed, screen: &ScreenData, object_defs: &ObjectDefs, group: GroupSync) -> Self {
+    pub fn new(seed: MapSeed, screen: &ScreenData, object_defs: &ObjectDefs, world: &WorldSync, index_screen: usize) -> Self {
+        let group = world.groups[index_screen];
         let anim_t = seed.hasher(RngStep::ScreenAnimationTime)
             .write(screen.position)
             .next_u32();
         
         let mut limiters = FxHashMap::default();
-        let mut counts = FxHashMap::default();
-
-        for LayerData(layer) in &screen.layers[4..] {
-            for tile in layer {
-                if tile.1 > 0
-                    && let Some(def) = object_defs.get(&ObjectId::from(tile))
-                    && def.limit != Limit::None
-                {
-                    let id = match &def.kind {
-                        ObjectKind::OverrideObject(tile_original) => ObjectId::from(tile_original),
-                        _ => ObjectId::from(tile),
-                    };
-                    counts.entry(id)
-                        .and_modify(|count| *count += 1)
-                        .or_insert(1);
-                }
-            }
-        }
+        let counts = count_limited_objects(screen, object_defs);
 
         for (id, count) in counts {
             let mut rng = seed.hasher(RngStep::Limiters)
                 .write(id)
                 .into_rng();
             let Some(def) = object_defs.get(&id) else { continue };
+            if def.limit.scope() != LimitScope::Screen {
+                let chosen = world.scoped_limits[index_screen]
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_default();
+                limiters.insert(id, Limiter::new(chosen));
+                continue;
+            }
             match def.limit {
                 Limit::None => {},
-                Limit::First { n } => {
+                Limit::First { n, .. } => {
                     let limiter = Limiter::take(n);
                     limiters.insert(id, limiter);
                 },
-                Limit::Random { n } => {
+                Limit::Random { n, .. } => {
                     let limiter = Limiter::choose_n(&mut rng, count, n);
                     limiters.insert(id, limiter);
                 },
-                Limit::LogNPlusOne => {
-                    let n = (1.0 + (count as f32).log2())
-                        .round()
-                        .clamp(0.0, count as f32)
-                        as usize;
-                    let limiter = Limiter::choose_n(&mut rng, count, n);
+                Limit::LogNPlusOne { .. } => {
+                    let limiter = Limiter::choose_n(&mut rng, count, log_n_plus_one(count));
                     limiters.insert(id, limiter);
                 },
             }
@@ -226,6 +509,7 @@ impl ScreenSync {
         Self {
             group,
             anim_t,
+            world_anim_t: world.anim_t,
             limiters,
         }
     }
@@ -248,14 +532,7 @@ impl Limiter {
     }
 
     pub fn choose_n(rng: &mut impl Rng, total: usize, n: usize) -> Self {
-        if total == 0 || n == 0 {
-            return Self { count: 0, chosen: Vec::new() };
-        }
-
-        let mut all = Vec::from_iter(0..total);
-        let (shuffled, _) = all.partial_shuffle(rng, n);
-
-        Self::new(shuffled.to_owned())
+        Self::new(choose_n_indices(rng, total, n))
     }
 
     pub fn increment(&mut self) -> bool {
@@ -273,3 +550,141 @@ impl Limiter {
         is_chosen
     }
 }
+
+fn choose_n_indices(rng: &mut impl Rng, total: usize, n: usize) -> Vec<usize> {
+    if total == 0 || n == 0 {
+        return Vec::new();
+    }
+
+    let mut all = Vec::from_iter(0..total);
+    let (shuffled, _) = all.partial_shuffle(rng, n);
+
+    shuffled.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::definitions::ObjectDef;
+
+    use super::*;
+
+    const SEED: MapSeed = MapSeed { seed: 0x5C0B_ED11_4175 };
+
+    fn limited_defs(limits: &[(ObjectId, Limit)]) -> ObjectDefs {
+        let mut defs = ObjectDefs::default();
+        for (id, limit) in limits {
+            defs.insert(*id, ObjectDef {
+                limit: *limit,
+                ..Default::default()
+            });
+        }
+        defs
+    }
+
+    /// The number of instances of `id` on each screen
+    fn limited_counts(id: ObjectId, counts: &[usize]) -> Vec<FxHashMap<ObjectId, usize>> {
+        counts.iter()
+            .map(|count| match count {
+                0 => FxHashMap::default(),
+                _ => FxHashMap::from_iter([(id, *count)]),
+            })
+            .collect()
+    }
+
+    fn choose(scope: LimitScope, defs: &ObjectDefs, counts: &[FxHashMap<ObjectId, usize>]) -> Vec<FxHashMap<ObjectId, Vec<usize>>> {
+        let members: Vec<_> = (0..counts.len()).collect();
+        let group_hash = match scope {
+            LimitScope::Group => Some(0xC0FFEE),
+            _ => None,
+        };
+        let mut scoped_limits = vec![FxHashMap::default(); counts.len()];
+        choose_scoped_limits(SEED, scope, group_hash, &members, counts, defs, &mut scoped_limits);
+        scoped_limits
+    }
+
+    #[test]
+    fn choose_n_indices_picks_distinct_indices() {
+        for total in 0..20 {
+            for n in 0..25 {
+                let mut rng = SEED.hasher(RngStep::Limiters).write((total, n)).into_rng();
+                let mut chosen = choose_n_indices(&mut rng, total, n);
+                assert_eq!(chosen.len(), n.min(total));
+                assert!(chosen.iter().all(|i| *i < total));
+                chosen.sort_unstable();
+                chosen.dedup();
+                assert_eq!(chosen.len(), n.min(total), "Duplicate index choosing {n} of {total}");
+            }
+        }
+    }
+
+    #[test]
+    fn choose_n_indices_is_deterministic() {
+        let choose_with_seed = |seed: MapSeed| {
+            let mut rng = seed.hasher(RngStep::Limiters).into_rng();
+            choose_n_indices(&mut rng, 100, 10)
+        };
+        assert_eq!(choose_with_seed(SEED), choose_with_seed(SEED));
+        assert_ne!(choose_with_seed(SEED), choose_with_seed(MapSeed { seed: SEED.seed + 1 }));
+    }
+
+    #[test]
+    fn first_limit_counts_across_group() {
+        let id = ObjectId::from(Tile(17, 3));
+        let defs = limited_defs(&[(id, Limit::First { n: 2, scope: LimitScope::Group })]);
+        let counts = limited_counts(id, &[1, 2, 0, 1]);
+
+        let scoped_limits = choose(LimitScope::Group, &defs, &counts);
+        assert_eq!(scoped_limits[0][&id], vec![0]);
+        assert_eq!(scoped_limits[1][&id], vec![0]);
+        assert!(!scoped_limits[2].contains_key(&id));
+        assert_eq!(scoped_limits[3][&id], Vec::<usize>::new());
+    }
+
+    #[test]
+    fn random_limit_picks_n_per_world() {
+        let id = ObjectId::from(Tile(17, 3));
+        let defs = limited_defs(&[(id, Limit::Random { n: 3, scope: LimitScope::World })]);
+        let counts = limited_counts(id, &[4, 0, 5, 2]);
+
+        let scoped_limits = choose(LimitScope::World, &defs, &counts);
+        assert_eq!(scoped_limits, choose(LimitScope::World, &defs, &counts));
+
+        let mut total_chosen = 0;
+        for (index_screen, count) in [4, 0, 5, 2].into_iter().enumerate() {
+            let Some(chosen) = scoped_limits[index_screen].get(&id) else {
+                assert_eq!(count, 0);
+                continue;
+            };
+            assert!(chosen.iter().all(|instance| *instance < count));
+            total_chosen += chosen.len();
+        }
+        assert_eq!(total_chosen, 3);
+    }
+
+    #[test]
+    fn log_n_plus_one_limit_counts_whole_scope() {
+        let id = ObjectId::from(Tile(17, 3));
+        let defs = limited_defs(&[(id, Limit::LogNPlusOne { scope: LimitScope::World })]);
+        let counts = limited_counts(id, &[3, 5]);
+
+        let scoped_limits = choose(LimitScope::World, &defs, &counts);
+        let total_chosen: usize = scoped_limits.iter()
+            .map(|chosen| chosen[&id].len())
+            .sum();
+        assert_eq!(total_chosen, log_n_plus_one(8));
+    }
+
+    #[test]
+    fn limits_in_other_scopes_are_skipped() {
+        let id_screen = ObjectId::from(Tile(17, 3));
+        let id_world = ObjectId::from(Tile(18, 6));
+        let defs = limited_defs(&[
+            (id_screen, Limit::First { n: 1, scope: LimitScope::Screen }),
+            (id_world, Limit::First { n: 1, scope: LimitScope::World }),
+        ]);
+        let counts = vec![FxHashMap::from_iter([(id_screen, 2), (id_world, 2)])];
+
+        let scoped_limits = choose(LimitScope::Group, &defs, &counts);
+        assert!(scoped_limits[0].is_empty());
+    }
+}
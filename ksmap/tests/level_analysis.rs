@@ -0,0 +1,274 @@
+#[allow(dead_code)]
+mod paths;
+
+use std::collections::{BTreeMap, HashSet};
+
+use image::{Rgba, RgbaImage};
+use ksmap::{
+    analysis,
+    check::{self, Severity},
+    definitions::{self, ObjectDefs},
+    diff::{self, ChangeKind},
+    drawing::{self, DrawContext, DrawOptions, SCREEN_SIZE},
+    graphics::Graphics,
+    links::{self, LinkKind, ScreenLink},
+    partition::Partition,
+    screen_graph::{self, ScreenEdge},
+    screen_map::ScreenMap,
+    search::{self, SearchQuery, TileQuery},
+    seed::MapSeed,
+    synchronization::{SyncOptions, WorldSync},
+};
+use libks::{ScreenCoord, map_bin::{self, Tile}, world_ini};
+use libks_ini::Ini;
+
+use paths::*;
+
+fn load_level(level_name: &str) -> (ScreenMap, Ini) {
+    let level_dir = WORLDS_DIR.join(level_name);
+    
+    let ini = world_ini::load_ini_from_dir(&level_dir)
+        .expect("World.ini should be valid");
+    let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))
+        .expect("Map.bin should be valid");
+    
+    (ScreenMap::new(screens), ini)
+}
+
+fn load_defs(ini: &Ini) -> ObjectDefs {
+    let mut object_defs = definitions::load_object_defs(DEFINITIONS_PATH.as_path())
+        .expect("Object definitions should be valid");
+    definitions::insert_custom_obj_defs(&mut object_defs, ini);
+    object_defs
+}
+
+fn check_level(level_name: &str) -> Vec<(Severity, Option<ScreenCoord>, String)> {
+    let (screens, ini) = load_level(level_name);
+    let object_defs = load_defs(&ini);
+    
+    check::check_level(&screens, &ini, &object_defs, &WORLDS_DIR.join(level_name))
+        .into_iter()
+        .map(|problem| (problem.severity, problem.screen, problem.message))
+        .collect()
+}
+
+#[test]
+fn links_follow_warps_and_shifts_with_their_objects() {
+    let (screens, ini) = load_level("ksmap - Links Test");
+    
+    // x1000y1000 has shift C keys but no shift C object, and x1001y1000 has no shift objects
+    assert_eq!(links::find_screen_links(&screens, &ini), vec![
+        ScreenLink { from: (1000, 1000), to: (1005, 1005), kind: LinkKind::Shift('A') },
+        ScreenLink { from: (1000, 1000), to: (1010, 1010), kind: LinkKind::Shift('B') },
+        ScreenLink { from: (1002, 1000), to: (1002, 997), kind: LinkKind::Warp('U') },
+        ScreenLink { from: (1002, 1000), to: (1010, 1010), kind: LinkKind::Warp('R') },
+        ScreenLink { from: (1005, 1005), to: (1055, 1005), kind: LinkKind::Shift('A') },
+    ]);
+}
+
+#[test]
+fn check_finds_broken_links_and_leftover_screen_sections() {
+    assert_eq!(check_level("ksmap - Links Test"), vec![
+        (Severity::Error, Some((1002, 1000)), "Warp U leads to x1002y997, which doesn't exist".to_owned()),
+        (Severity::Error, Some((1005, 1005)), "Shift A leads to x1055y1005, which doesn't exist".to_owned()),
+        (Severity::Info, Some((1020, 1020)), "[x1020y1020] is for a screen that doesn't exist".to_owned()),
+    ]);
+}
+
+#[test]
+fn check_finds_missing_custom_object_images() {
+    // Custom Object 1 has its image and Custom Object 4 keeps the graphics of the object it overrides
+    assert_eq!(check_level("ksmap - Check Test"), vec![
+        (Severity::Error, None, "[Custom Object 2] uses image \"Missing.png\", which isn't in Custom Objects".to_owned()),
+        (Severity::Error, None, "[Custom Object 5] has no image".to_owned()),
+        (Severity::Error, None, "[World] Powers uses image \"Missing Powers.png\", which isn't in Custom Objects".to_owned()),
+        (Severity::Error, Some((1000, 1000)), "Uses custom object 255-6, but there's no [Custom Object 6]".to_owned()),
+        (Severity::Warning, None, "[Custom Object B3] uses image \"Gone.png\", which isn't in Custom Objects".to_owned()),
+    ]);
+}
+
+#[test]
+fn screen_graph_has_walks_and_links_between_existing_screens() {
+    let (screens, ini) = load_level("ksmap - Links Test");
+    let graph = screen_graph::build_screen_graph(&screens, &ini);
+    
+    let edges: HashSet<_> = graph.edge_indices()
+        .map(|edge| {
+            let (from, to) = graph.edge_endpoints(edge).unwrap();
+            (graph[from], graph[to], graph[edge])
+        })
+        .collect();
+    // The warp up from x1002y1000 and the shift from x1005y1005 lead to screens that don't exist
+    assert_eq!(graph.node_count(), 5);
+    assert_eq!(edges, HashSet::from([
+        ((1000, 1000), (1001, 1000), ScreenEdge::Walk('R')),
+        ((1001, 1000), (1000, 1000), ScreenEdge::Walk('L')),
+        ((1001, 1000), (1002, 1000), ScreenEdge::Walk('R')),
+        ((1002, 1000), (1001, 1000), ScreenEdge::Walk('L')),
+        ((1000, 1000), (1005, 1005), ScreenEdge::Link(LinkKind::Shift('A'))),
+        ((1000, 1000), (1010, 1010), ScreenEdge::Link(LinkKind::Shift('B'))),
+        ((1002, 1000), (1010, 1010), ScreenEdge::Link(LinkKind::Warp('R'))),
+    ]));
+}
+
+#[test]
+fn search_matches_objects_through_the_objects_they_override() {
+    let (screens, ini) = load_level("ksmap - Collision Map Test");
+    let object_defs = load_defs(&ini);
+    let find = |query: &SearchQuery| search::find_tiles(&screens, query, &object_defs)
+        .into_iter()
+        .map(|found| (found.object, found.original, found.layer, found.tile))
+        .collect::<Vec<_>>();
+    
+    let mut query = SearchQuery::new(TileQuery::Tile(Tile(4, 1)));
+    assert_eq!(find(&query), vec![("4-1".to_owned(), None, 4, 30)]);
+    
+    query.match_originals = true;
+    assert_eq!(find(&query), vec![
+        ("4-1".to_owned(), None, 4, 30),
+        ("255-1".to_owned(), Some("4-1".to_owned()), 5, 31),
+    ]);
+    
+    let mut query = SearchQuery::new(TileQuery::Bank(0));
+    assert_eq!(find(&query), vec![
+        ("0-14".to_owned(), None, 4, 34),
+        ("0-22".to_owned(), None, 6, 32),
+    ]);
+    
+    // On tile layers, bank 0 is tileset A
+    query.layers = search::TILE_LAYERS.collect();
+    assert_eq!(find(&query), vec![("0-1".to_owned(), None, 3, 0)]);
+}
+
+#[test]
+fn stats_count_empty_screens_and_unfilled_bounds_separately() {
+    let (screens, ini) = load_level("ksmap - Stats Test");
+    let stats = analysis::level_stats(&screens, &ini);
+    
+    assert_eq!(stats.screens, 4);
+    assert_eq!(stats.empty_screens, 2);
+    assert_eq!(stats.empty_screen_ratio, 0.5);
+    assert_eq!(stats.unfilled_bounds_ratio, 0.5);
+    assert_eq!(stats.objects, 1);
+    assert_eq!(stats.tilesets, BTreeMap::from([(1, 1), (4, 1)]));
+    assert_eq!(stats.gradients, BTreeMap::from([(1, 2), (2, 2)]));
+    assert_eq!(stats.overlay_screens, vec![(1003, 1000)]);
+    
+    let assets: Vec<_> = stats.screen_assets.iter()
+        .map(|assets| (assets.position, assets.tileset_a, assets.tileset_b, assets.gradient))
+        .collect();
+    assert_eq!(assets, vec![
+        ((1000, 1000), 1, 2, 1),
+        ((1001, 1000), 1, 2, 1),
+        ((1003, 1000), 1, 2, 2),
+        ((1003, 1001), 3, 4, 2),
+    ]);
+}
+
+#[test]
+fn exact_duplicates_are_grouped() {
+    let (screens, _) = load_level("ksmap - Duplicates Test");
+    
+    // x1002y1000 has the same objects as the exact duplicates, but a different tileset
+    let duplicates = analysis::find_duplicate_screens(&screens, 0);
+    assert_eq!(duplicates.exact, vec![vec![(1000, 1000), (1001, 1000), (1000, 1001)]]);
+    assert!(duplicates.near.is_empty());
+}
+
+#[test]
+fn near_duplicates_chain_together() {
+    let (screens, _) = load_level("ksmap - Duplicates Test");
+    
+    // x1000y1002 is one object away from x1001y1002, which is one object away from the exact
+    // duplicates, but two away from them itself
+    let duplicates = analysis::find_duplicate_screens(&screens, 1);
+    assert_eq!(duplicates.exact, vec![vec![(1000, 1000), (1001, 1000), (1000, 1001)]]);
+    assert_eq!(duplicates.near.len(), 1);
+    assert_eq!(duplicates.near[0].screens, vec![
+        (1000, 1000), (1001, 1000), (1002, 1000), (1000, 1001), (1000, 1002), (1001, 1002),
+    ]);
+    assert_eq!(duplicates.near[0].max_difference, 1);
+    
+    let duplicates = analysis::find_duplicate_screens(&screens, 2);
+    assert_eq!(duplicates.near.len(), 1);
+    assert_eq!(duplicates.near[0].screens, vec![
+        (1000, 1000), (1001, 1000), (1002, 1000), (1003, 1000), (1000, 1001), (1000, 1002), (1001, 1002),
+    ]);
+    assert_eq!(duplicates.near[0].max_difference, 2);
+}
+
+#[test]
+fn diff_finds_added_removed_and_modified_screens() {
+    let (old, _) = load_level("ksmap - Diff Test (Old)");
+    let (new, _) = load_level("ksmap - Diff Test (New)");
+    
+    let changes: Vec<_> = diff::diff_screen_maps(&old, &new)
+        .into_iter()
+        .map(|change| (change.position, change.kind, change.difference))
+        .collect();
+    assert_eq!(changes, vec![
+        ((1001, 1000), ChangeKind::Modified, 2),
+        ((1002, 1000), ChangeKind::Removed, 2),
+        ((1003, 1000), ChangeKind::Added, 1),
+        ((1000, 1001), ChangeKind::Modified, 1),
+    ]);
+}
+
+#[test]
+fn collision_map_masks_tiles_and_marks_object_categories() {
+    const BACKGROUND: Rgba<u8> = Rgba([24, 24, 32, 255]);
+    const SCENERY: Rgba<u8> = Rgba([56, 56, 68, 255]);
+    const SOLID: Rgba<u8> = Rgba([220, 220, 220, 255]);
+    const HAZARD: Rgba<u8> = Rgba([255, 48, 48, 255]);
+    const COLLECTABLE: Rgba<u8> = Rgba([255, 220, 0, 255]);
+    const INTERACTIVE: Rgba<u8> = Rgba([64, 160, 255, 255]);
+    
+    let level_name = "ksmap - Collision Map Test";
+    let level_dir = WORLDS_DIR.join(level_name);
+    let (screens, ini) = load_level(level_name);
+    
+    let object_defs = load_defs(&ini);
+    
+    let mut gfx = Graphics::new(
+        DATA_DIR.as_path(),
+        &level_dir,
+        TEMPLATES_DIR.as_path(),
+        &object_defs,
+    );
+    gfx.load_tilesets(&[1])
+        .expect("IO error or corrupt image while loading tilesets");
+    
+    let seed = MapSeed { seed: 1 };
+    let world_sync = WorldSync::new(seed, &screens, &object_defs, &SyncOptions::default());
+    let draw_context = DrawContext {
+        seed,
+        screens: &screens,
+        gfx: &gfx,
+        defs: &object_defs,
+        ini: &ini,
+        world_sync: &world_sync,
+        options: DrawOptions {
+            editor_only: false,
+            collision_map: true,
+        },
+    };
+    let image = drawing::draw_partition(draw_context, &Partition::new(vec![(1000, 1000)]))
+        .expect("IO error while drawing map");
+    
+    // The top-left pixel of a tile, where the outline of a marker starts
+    let tile_pixel = |image: &RgbaImage, index: u32| *image.get_pixel(index % 25 * 24, index / 25 * 24);
+    
+    assert_eq!(image.dimensions(), SCREEN_SIZE);
+    // Tile 1 of the tileset is solid and tile 2 only has pixels in its left half
+    assert_eq!(*image.get_pixel(0, 0), SOLID);
+    assert_eq!(*image.get_pixel(23, 23), SOLID);
+    assert_eq!(*image.get_pixel(24, 0), SCENERY);
+    assert_eq!(*image.get_pixel(35, 23), SCENERY);
+    assert_eq!(*image.get_pixel(36, 0), BACKGROUND);
+    // 4-1, a custom object overriding 4-1, a key, 1-1 without a category, and a shift
+    assert_eq!(tile_pixel(&image, 30), HAZARD);
+    assert_eq!(tile_pixel(&image, 31), HAZARD);
+    assert_eq!(tile_pixel(&image, 32), COLLECTABLE);
+    assert_eq!(tile_pixel(&image, 33), BACKGROUND);
+    assert_eq!(tile_pixel(&image, 34), INTERACTIVE);
+}
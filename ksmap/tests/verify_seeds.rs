@@ -11,7 +11,7 @@ use ksmap::{
     partition::{GridPartitioner, Partitioner},
     screen_map::ScreenMap,
     seed::MapSeed,
-    synchronization::{SyncOptions, WorldSync},
+    synchronization::{LaserStrategy, SyncOptions, WorldSync},
 };
 use libks::{map_bin, world_ini};
 use serde::Deserialize;
@@ -21,6 +21,11 @@ use paths::*;
 #[derive(Deserialize)]
 struct SeedIndexEntry {
     seeds: Vec<MapSeed>,
+    /// Extra object definitions to load on top of the stock ones, relative to the seeds directory
+    definitions: Option<String>,
+    /// Whether to sync screens whose corner objects touch diagonally
+    #[serde(default)]
+    sync_diagonals: bool,
 }
 
 static SEED_INDEX: LazyLock<HashMap<String, SeedIndexEntry>> = LazyLock::new(|| {
@@ -30,7 +35,7 @@ static SEED_INDEX: LazyLock<HashMap<String, SeedIndexEntry>> = LazyLock::new(||
         .expect("index.toml should be valid")
 });
 
-fn verify_seeds(level_name: &str, seeds: &[MapSeed]) {
+fn verify_seeds(level_name: &str, entry: &SeedIndexEntry) {
     let level_dir = WORLDS_DIR.join(level_name);
     
     let ini = world_ini::load_ini_from_dir(&level_dir)
@@ -38,7 +43,9 @@ fn verify_seeds(level_name: &str, seeds: &[MapSeed]) {
     let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))
         .expect("Map.bin should be valid");
     
-    let mut object_defs = definitions::load_object_defs(DEFINITIONS_PATH.as_path())
+    let extra_defs_path = entry.definitions.as_ref()
+        .map(|path| SEEDS_DIR.join(path));
+    let mut object_defs = definitions::load_object_defs_with_extra(DEFINITIONS_PATH.as_path(), extra_defs_path.as_deref())
         .expect("Object definitions should be valid");
     definitions::insert_custom_obj_defs(&mut object_defs, &ini);
     
@@ -66,12 +73,14 @@ fn verify_seeds(level_name: &str, seeds: &[MapSeed]) {
     
     let draw_options = DrawOptions {
         editor_only: false,
+        collision_map: false,
     };
     let sync_options = SyncOptions {
-        maximize_visible_lasers: true,
+        laser_strategy: LaserStrategy::Maximize,
+        sync_diagonals: entry.sync_diagonals,
     };
     
-    for seed in seeds.iter().cloned() {
+    for seed in entry.seeds.iter().cloned() {
         let world_sync = WorldSync::new(seed, &screen_map, &object_defs, &sync_options);
         
         let draw_context = DrawContext {
@@ -103,7 +112,7 @@ macro_rules! test_case {
         #[test]
         fn $test_name() {
             let entry = SEED_INDEX.get($level_name).expect("Test was missing from index");
-            verify_seeds($level_name, &entry.seeds);
+            verify_seeds($level_name, entry);
         }
     }
 }
@@ -124,3 +133,6 @@ test_case!(lit_knob_umbrella_and_keys_test, "Lit Knob - Umbrella and Keys Test")
 test_case!(lit_knob_wrong_co_resolution_test, "Lit Knob - Wrong CO Resolution Test");
 test_case!(robin_horizontal_flip_test, "Robin - Horizontal Flip Test");
 test_case!(robin_unsupported_oco_test, "Robin - Unsupported OCO Test");
+test_case!(ksmap_sync_diagonal_test, "ksmap - Sync Diagonal Test");
+test_case!(ksmap_sync_direction_test, "ksmap - Sync Direction Test");
+test_case!(ksmap_sync_offset_test, "ksmap - Sync Offset Test");
@@ -12,7 +12,7 @@ use ksmap::{
     partition::{GridPartitioner, Partitioner},
     screen_map::ScreenMap,
     seed::MapSeed,
-    synchronization::{SyncOptions, WorldSync},
+    synchronization::{LaserStrategy, SyncOptions, WorldSync},
 };
 use libks::{map_bin, world_ini};
 use serde::{Deserialize, Serialize};
@@ -45,9 +45,16 @@ fn main() -> Result<()> {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 struct SeedIndexEntry {
     seeds: Vec<MapSeed>,
+    /// Extra object definitions to load on top of the stock ones, relative to the seeds directory.
+    /// Add this to a level's entry by hand before making its seeds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    definitions: Option<String>,
+    /// Whether to sync screens whose corner objects touch diagonally
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    sync_diagonals: bool,
 }
 
 fn make_seeds(args: MakeSeedsArgs) -> Result<()> {
@@ -93,11 +100,10 @@ fn make_seeds(args: MakeSeedsArgs) -> Result<()> {
             std::fs::remove_dir_all(&output_dir)?;
         }
         std::fs::create_dir_all(&output_dir)?;
-        render_seeds(&level_dir, &seeds, &output_dir);
         
-        seed_index.insert(level_name, SeedIndexEntry {
-            seeds,
-        });
+        let entry = seed_index.entry(level_name).or_default();
+        entry.seeds = seeds;
+        render_seeds(&level_dir, entry, &output_dir);
     }
     
     let seed_index_serialized = toml::to_string_pretty(&seed_index)?;
@@ -106,13 +112,15 @@ fn make_seeds(args: MakeSeedsArgs) -> Result<()> {
     Ok(())
 }
 
-fn render_seeds(level_dir: &Path, seeds: &[MapSeed], output_dir: &Path) {
+fn render_seeds(level_dir: &Path, entry: &SeedIndexEntry, output_dir: &Path) {
     let ini = world_ini::load_ini_from_dir(&level_dir)
         .expect("World.ini should be valid");
     let screens = map_bin::parse_map_file(level_dir.join("Map.bin"))
         .expect("Map.bin should be valid");
     
-    let mut object_defs = definitions::load_object_defs(DEFINITIONS_PATH.as_path())
+    let extra_defs_path = entry.definitions.as_ref()
+        .map(|path| SEEDS_DIR.join(path));
+    let mut object_defs = definitions::load_object_defs_with_extra(DEFINITIONS_PATH.as_path(), extra_defs_path.as_deref())
         .expect("Object definitions should be valid");
     definitions::insert_custom_obj_defs(&mut object_defs, &ini);
     
@@ -140,12 +148,14 @@ fn render_seeds(level_dir: &Path, seeds: &[MapSeed], output_dir: &Path) {
     
     let draw_options = DrawOptions {
         editor_only: false,
+        collision_map: false,
     };
     let sync_options = SyncOptions {
-        maximize_visible_lasers: true,
+        laser_strategy: LaserStrategy::Maximize,
+        sync_diagonals: entry.sync_diagonals,
     };
     
-    for seed in seeds.iter().cloned() {
+    for seed in entry.seeds.iter().cloned() {
         let world_sync = WorldSync::new(seed, &screen_map, &object_defs, &sync_options);
         
         let draw_context = DrawContext {